@@ -0,0 +1,616 @@
+mod secrets;
+
+use bip39::Mnemonic;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use richiesafe_core::crypto::kdf::{self, KdfParams};
+use richiesafe_core::crypto::rng;
+use richiesafe_core::models::entry::{
+    CardData, EntryKind, IdentityData, LoginData, NoteData, VaultEntry,
+};
+use richiesafe_core::vault::header::VaultType;
+use richiesafe_core::storage::{write_private, VaultStore};
+use richiesafe_core::strength::{self, StrengthPolicy};
+use richiesafe_core::vault::attempts::ThrottlePolicy;
+use richiesafe_core::vault::ops::{self, VaultHandle};
+use secrets::Secrets;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use uuid::Uuid;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// Timestamped copies of the vault file kept next to it
+const BACKUPS: usize = 5;
+const DEVICE_KEY_SIZE: usize = 32;
+
+#[derive(Parser)]
+#[command(name = "richiesafe", version, about = "Manage RichieSafe .rsafe vaults")]
+struct Cli {
+    /// Vault file to operate on
+    #[arg(short, long, global = true, env = "RICHIESAFE_VAULT", default_value = "vault.rsafe")]
+    vault: PathBuf,
+
+    /// Read secrets one per line from stdin instead of prompting on the TTY
+    #[arg(long, global = true)]
+    stdin: bool,
+
+    /// Accept a new PIN that fails the recommended strength policy
+    #[arg(long, global = true)]
+    allow_weak_pin: bool,
+
+    /// Key that signs the failed-attempt record, created on first use
+    /// [default: ~/.config/richiesafe/device.key]
+    #[arg(long, global = true, env = "RICHIESAFE_DEVICE_KEY")]
+    device_key: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a new vault protected by a PIN and a generated recovery phrase
+    Init {
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+        /// Create a decoy vault instead of a real one
+        #[arg(long)]
+        decoy: bool,
+    },
+    /// Check that a secret opens the vault
+    Unlock,
+    /// List entries
+    #[command(alias = "list")]
+    Ls,
+    /// Show one entry; secrets are masked unless --reveal is given
+    Show {
+        /// Entry id or unique id prefix
+        id: String,
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Add an entry; its secret (password, CVV, note text, document number) is prompted for
+    Add {
+        #[arg(long, value_enum, default_value_t = Kind::Login)]
+        kind: Kind,
+        #[arg(long)]
+        title: String,
+        #[command(flatten)]
+        fields: EntryFields,
+    },
+    /// Edit an entry in place
+    Edit {
+        /// Entry id or unique id prefix
+        id: String,
+        #[arg(long)]
+        title: Option<String>,
+        #[command(flatten)]
+        fields: EntryFields,
+        /// Prompt for a new secret
+        #[arg(long)]
+        secret: bool,
+    },
+    /// Delete an entry
+    Rm {
+        /// Entry id or unique id prefix
+        id: String,
+    },
+    /// Change the PIN (the current PIN is required)
+    ChangePin,
+    /// Replace the recovery phrase with a newly generated one
+    RotateRecovery,
+    /// Estimate how hard a PIN or password is to guess
+    Strength,
+    /// Write all entries as plaintext JSON
+    Export {
+        /// Output file (stdout if omitted)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Add entries from a JSON file produced by `export`
+    Import {
+        input: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Kind {
+    Login,
+    Card,
+    Note,
+    Identity,
+}
+
+impl Kind {
+    /// Same names as `EntryKind::name`.
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Login => "login",
+            Kind::Card => "card",
+            Kind::Note => "note",
+            Kind::Identity => "identity",
+        }
+    }
+}
+
+#[derive(Args, Default)]
+struct EntryFields {
+    /// Login username
+    #[arg(long)]
+    username: Option<String>,
+    /// Login URL
+    #[arg(long)]
+    url: Option<String>,
+    /// Card holder
+    #[arg(long)]
+    holder: Option<String>,
+    /// Card number
+    #[arg(long)]
+    number: Option<String>,
+    /// Card expiry (MM/YY)
+    #[arg(long)]
+    expiry: Option<String>,
+    /// Identity full name
+    #[arg(long)]
+    full_name: Option<String>,
+    #[arg(long)]
+    email: Option<String>,
+    #[arg(long)]
+    phone: Option<String>,
+    #[arg(long)]
+    address: Option<String>,
+    #[arg(long)]
+    birth_date: Option<String>,
+    /// File name (file entries only)
+    #[arg(long)]
+    file_name: Option<String>,
+    /// Free-form notes
+    #[arg(long)]
+    notes: Option<String>,
+    /// Tag (repeatable)
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut secrets = Secrets::new(cli.stdin);
+
+    match run(&cli, &mut secrets) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli, secrets: &mut Secrets) -> CliResult<()> {
+    let path = cli.vault.as_path();
+    let store = VaultStore::new(path, BACKUPS);
+    let vault = Vault { store: &store, device_key: device_key_path(cli) };
+    if !cli.allow_weak_pin {
+        strength::set_policy(StrengthPolicy::RECOMMENDED);
+    }
+
+    match &cli.command {
+        Command::Init { force, decoy } => {
+            if path.exists() && !force {
+                return Err(format!("{} already exists (use --force to overwrite)", path.display()).into());
+            }
+            let pin = secrets.read_new("New PIN: ", "Repeat PIN: ")?;
+            let recovery = generate_recovery_phrase();
+            let vault_type = if *decoy { VaultType::Decoy } else { VaultType::Real };
+
+            let params = device_params()?;
+            let blob = ops::create_vault(vault_type, &pin, &recovery, params, params)?;
+            store.save(&blob)?;
+            // A record left by the overwritten vault would read as tampered and lock this one
+            match fs::remove_file(store.attempts_path()) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+
+            println!("Created {}", path.display());
+            println!("Recovery phrase (write it down, it is not shown again):\n{}", *recovery);
+        }
+        Command::Unlock => {
+            let handle = vault.open(secrets)?;
+            println!("Vault unlocked ({} entries)", handle.state.entries.len());
+        }
+        Command::Ls => {
+            let handle = vault.open(secrets)?;
+            for e in &handle.state.entries {
+                println!("{}  {:<8}  {}", e.id, e.kind.name(), e.title);
+            }
+        }
+        Command::Show { id, reveal } => {
+            let handle = vault.open(secrets)?;
+            let id = resolve_id(&handle, id)?;
+            print_entry(handle.get_entry(&id)?, *reveal);
+        }
+        Command::Add { kind, title, fields } => {
+            check_flags(kind.name(), fields)?;
+            let mut handle = vault.open(secrets)?;
+            let secret = secrets.read(secret_prompt(*kind))?;
+            let mut entry = VaultEntry::new(title, build_kind(*kind, fields, (!secret.is_empty()).then(|| secret.to_string())));
+            entry.notes = fields.notes.clone();
+            entry.tags = fields.tags.clone();
+
+            let id = handle.add_entry(entry);
+            store.save(&handle.seal()?)?;
+            println!("{id}");
+        }
+        Command::Edit { id, title, fields, secret } => {
+            let mut handle = vault.open(secrets)?;
+            let id = resolve_id(&handle, id)?;
+            let kind = &handle.get_entry(&id)?.kind;
+            check_flags(kind.name(), fields)?;
+            let new_secret = match (secret, kind_of(kind)) {
+                (true, Some(kind)) => Some(secrets.read(secret_prompt(kind))?.to_string()),
+                (true, None) => return Err(format!("{} entries have no secret to edit", kind.name()).into()),
+                (false, _) => None,
+            };
+
+            handle.update_entry(&id, |e| {
+                if let Some(t) = title {
+                    e.title = t.clone();
+                }
+                apply_fields(e, fields, new_secret);
+            })?;
+            store.save(&handle.seal()?)?;
+        }
+        Command::Rm { id } => {
+            let mut handle = vault.open(secrets)?;
+            let id = resolve_id(&handle, id)?;
+            handle.delete_entry(&id)?;
+            store.save(&handle.seal()?)?;
+        }
+        Command::ChangePin => {
+            let old_pin = secrets.read_required("Current PIN: ")?;
+            let mut handle = vault.unlock(&old_pin)?;
+            let new_pin = secrets.read_new("New PIN: ", "Repeat new PIN: ")?;
+
+            let blob = ops::change_pin(&mut handle, &old_pin, &new_pin, device_params()?)?;
+            store.save_rekeyed(&blob)?;
+            println!("PIN changed (backups made under the old PIN were deleted)");
+        }
+        Command::RotateRecovery => {
+            let mut handle = vault.open(secrets)?;
+            let recovery = generate_recovery_phrase();
+
+            let blob = ops::rotate_recovery(&mut handle, &recovery, device_params()?)?;
+            store.save_rekeyed(&blob)?;
+            println!("New recovery phrase (the old one no longer opens this vault or its backups):\n{}", *recovery);
+        }
+        Command::Strength => {
+            let secret = secrets.read("Secret: ")?;
+            let estimate = strength::estimate(&secret);
+            println!("score:    {}/4 (~{:.0} bits)", estimate.score, estimate.entropy_bits);
+            for reason in &estimate.reasons {
+                println!("weakness: {}", reason.name());
+            }
+        }
+        Command::Export { out } => {
+            let handle = vault.open(secrets)?;
+            let json = zeroize::Zeroizing::new(serde_json::to_string_pretty(&handle.state.entries)?);
+            match out {
+                Some(out) => {
+                    write_private(out, json.as_bytes())?;
+                    eprintln!("Exported {} entries to {} (plaintext!)", handle.state.entries.len(), out.display());
+                }
+                None => io::stdout().write_all(json.as_bytes())?,
+            }
+        }
+        Command::Import { input } => {
+            let json = zeroize::Zeroizing::new(fs::read_to_string(input)?);
+            let entries: Vec<VaultEntry> = serde_json::from_str(&json)?;
+            // Refuse the whole file rather than import part of it
+            for entry in &entries {
+                for field in &entry.fields {
+                    field.validate()?;
+                }
+            }
+            let mut handle = vault.open(secrets)?;
+
+            let count = entries.len();
+            for mut entry in entries {
+                // Keep both copies if the id is already taken
+                if handle.find_by_id(&entry.id).is_some() {
+                    entry.id = Uuid::new_v4();
+                }
+                handle.add_entry(entry);
+            }
+            store.save(&handle.seal()?)?;
+            println!("Imported {count} entries");
+        }
+    }
+    Ok(())
+}
+
+/// The vault file plus the key its attempt record is signed with.
+struct Vault<'a> {
+    store: &'a VaultStore,
+    device_key: PathBuf,
+}
+
+impl Vault<'_> {
+    fn open(&self, secrets: &mut Secrets) -> CliResult<VaultHandle> {
+        let secret = secrets.read_required("PIN or recovery phrase: ")?;
+        self.unlock(&secret)
+    }
+
+    /// Every unlock goes through the attempt record, so wrong guesses are throttled.
+    fn unlock(&self, secret: &str) -> CliResult<VaultHandle> {
+        let device_key = load_device_key(&self.device_key)?;
+        let opened = self.store.open(secret, &device_key, &ThrottlePolicy::default())?;
+        if let Some(backup) = &opened.restored_from {
+            eprintln!("warning: {} was damaged; restored from {}", self.store.path().display(), backup.display());
+        }
+        Ok(opened.handle)
+    }
+}
+
+fn device_key_path(cli: &Cli) -> PathBuf {
+    if let Some(path) = &cli.device_key {
+        return path.clone();
+    }
+    match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".config/richiesafe/device.key"),
+        None => PathBuf::from(".richiesafe-device.key"),
+    }
+}
+
+/// Reads the device key, generating it the first time.
+fn load_device_key(path: &Path) -> CliResult<zeroize::Zeroizing<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(key) if key.len() == DEVICE_KEY_SIZE => return Ok(zeroize::Zeroizing::new(key)),
+        Ok(_) => return Err(format!("{} is not a device key", path.display()).into()),
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        Err(_) => {}
+    }
+    let key = zeroize::Zeroizing::new(rng::generate_bytes(DEVICE_KEY_SIZE));
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    write_private(path, &key)?;
+    Ok(key)
+}
+
+/// Argon2id cost tuned to this machine (see `kdf::calibrate`).
+fn device_params() -> CliResult<KdfParams> {
+    Ok(kdf::calibrate(kdf::DEFAULT_TARGET_MS, kdf::DEFAULT_MAX_MEMORY_KIB)?)
+}
+
+fn generate_recovery_phrase() -> zeroize::Zeroizing<String> {
+    let entropy = zeroize::Zeroizing::new(rng::generate_bytes(16)); // 128 bits for 12 words
+    let mnemonic = Mnemonic::from_entropy(&entropy).expect("16 bytes is valid BIP39 entropy");
+    zeroize::Zeroizing::new(mnemonic.to_string())
+}
+
+/// Accepts a full UUID or an unambiguous prefix of one.
+fn resolve_id(handle: &VaultHandle, id: &str) -> CliResult<Uuid> {
+    if let Ok(id) = Uuid::parse_str(id) {
+        return Ok(id);
+    }
+    let needle = id.to_ascii_lowercase();
+    let mut matches = handle.state.entries.iter()
+        .map(|e| e.id)
+        .filter(|candidate| candidate.to_string().starts_with(&needle));
+
+    match (matches.next(), matches.next()) {
+        (Some(found), None) => Ok(found),
+        (None, _) => Err(format!("no entry matches '{id}'").into()),
+        (Some(_), Some(_)) => Err(format!("'{id}' matches several entries").into()),
+    }
+}
+
+/// `None` for file entries, which have no prompted secret.
+fn kind_of(kind: &EntryKind) -> Option<Kind> {
+    match kind {
+        EntryKind::Login(_) => Some(Kind::Login),
+        EntryKind::Card(_) => Some(Kind::Card),
+        EntryKind::Note(_) => Some(Kind::Note),
+        EntryKind::Identity(_) => Some(Kind::Identity),
+        EntryKind::File(_) => None,
+    }
+}
+
+/// Rejects flags for fields that entries of `kind` do not have, instead of ignoring them.
+fn check_flags(kind: &str, f: &EntryFields) -> CliResult<()> {
+    let flags = [
+        ("--username", f.username.is_some(), "login"),
+        ("--url", f.url.is_some(), "login"),
+        ("--holder", f.holder.is_some(), "card"),
+        ("--number", f.number.is_some(), "card"),
+        ("--expiry", f.expiry.is_some(), "card"),
+        ("--full-name", f.full_name.is_some(), "identity"),
+        ("--email", f.email.is_some(), "identity"),
+        ("--phone", f.phone.is_some(), "identity"),
+        ("--address", f.address.is_some(), "identity"),
+        ("--birth-date", f.birth_date.is_some(), "identity"),
+        ("--file-name", f.file_name.is_some(), "file"),
+    ];
+    match flags.iter().find(|(_, given, owner)| *given && *owner != kind) {
+        Some((flag, ..)) => Err(format!("{kind} entries have no {flag}").into()),
+        None => Ok(()),
+    }
+}
+
+fn secret_prompt(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Login => "Password (empty for none): ",
+        Kind::Card => "CVV (empty for none): ",
+        Kind::Note => "Note text: ",
+        Kind::Identity => "Document number (empty for none): ",
+    }
+}
+
+fn build_kind(kind: Kind, f: &EntryFields, secret: Option<String>) -> EntryKind {
+    match kind {
+        Kind::Login => EntryKind::Login(LoginData {
+            username: f.username.clone().unwrap_or_default(),
+            password: secret,
+            url: f.url.clone(),
+        }),
+        Kind::Card => EntryKind::Card(CardData {
+            holder: f.holder.clone(),
+            number: f.number.clone().unwrap_or_default(),
+            expiry: f.expiry.clone(),
+            cvv: secret,
+        }),
+        Kind::Note => EntryKind::Note(NoteData { text: secret.unwrap_or_default() }),
+        Kind::Identity => EntryKind::Identity(IdentityData {
+            full_name: f.full_name.clone().unwrap_or_default(),
+            email: f.email.clone(),
+            phone: f.phone.clone(),
+            address: f.address.clone(),
+            birth_date: f.birth_date.clone(),
+            document_number: secret,
+        }),
+    }
+}
+
+/// Applies only the flags that were given; `secret` replaces the kind's secret field.
+fn apply_fields(e: &mut VaultEntry, f: &EntryFields, secret: Option<String>) {
+    fn set(slot: &mut Option<String>, value: &Option<String>) {
+        if let Some(v) = value {
+            *slot = (!v.is_empty()).then(|| v.clone());
+        }
+    }
+
+    match &mut e.kind {
+        EntryKind::Login(l) => {
+            if let Some(v) = &f.username { l.username = v.clone(); }
+            set(&mut l.url, &f.url);
+            if let Some(s) = secret { l.password = (!s.is_empty()).then_some(s); }
+        }
+        EntryKind::Card(c) => {
+            set(&mut c.holder, &f.holder);
+            if let Some(v) = &f.number { c.number = v.clone(); }
+            set(&mut c.expiry, &f.expiry);
+            if let Some(s) = secret { c.cvv = (!s.is_empty()).then_some(s); }
+        }
+        EntryKind::Note(n) => {
+            if let Some(s) = secret { n.text = s; }
+        }
+        EntryKind::Identity(i) => {
+            if let Some(v) = &f.full_name { i.full_name = v.clone(); }
+            set(&mut i.email, &f.email);
+            set(&mut i.phone, &f.phone);
+            set(&mut i.address, &f.address);
+            set(&mut i.birth_date, &f.birth_date);
+            if let Some(s) = secret { i.document_number = (!s.is_empty()).then_some(s); }
+        }
+        EntryKind::File(file) => {
+            if let Some(v) = &f.file_name { file.file_name = v.clone(); }
+        }
+    }
+    set(&mut e.notes, &f.notes);
+    if !f.tags.is_empty() {
+        e.tags = f.tags.clone();
+    }
+}
+
+fn print_entry(e: &VaultEntry, reveal: bool) {
+    let secret = |s: &Option<String>| match (s, reveal) {
+        (None, _) => "-".to_string(),
+        (Some(s), true) => s.clone(),
+        (Some(_), false) => "********".to_string(),
+    };
+    let plain = |s: &Option<String>| s.clone().unwrap_or_else(|| "-".to_string());
+
+    println!("id:       {}", e.id);
+    println!("title:    {}", e.title);
+    println!("kind:     {}", e.kind.name());
+    match &e.kind {
+        EntryKind::Login(l) => {
+            println!("username: {}", l.username);
+            println!("password: {}", secret(&l.password));
+            println!("url:      {}", plain(&l.url));
+        }
+        EntryKind::Card(c) => {
+            println!("holder:   {}", plain(&c.holder));
+            println!("number:   {}", secret(&Some(c.number.clone())));
+            println!("expiry:   {}", plain(&c.expiry));
+            println!("cvv:      {}", secret(&c.cvv));
+        }
+        EntryKind::Note(n) => println!("text:     {}", secret(&Some(n.text.clone()))),
+        EntryKind::Identity(i) => {
+            println!("name:     {}", i.full_name);
+            println!("email:    {}", plain(&i.email));
+            println!("phone:    {}", plain(&i.phone));
+            println!("address:  {}", plain(&i.address));
+            println!("born:     {}", plain(&i.birth_date));
+            println!("document: {}", secret(&i.document_number));
+        }
+        EntryKind::File(f) => {
+            println!("file:     {} ({} bytes inline)", f.file_name, f.data.len());
+        }
+    }
+    println!("notes:    {}", plain(&e.notes));
+    for f in &e.fields {
+        let value = match f.is_hidden() {
+            true => secret(&Some(f.value.clone())),
+            false => f.value.clone(),
+        };
+        println!("{}: {value}", f.name);
+    }
+    if !e.tags.is_empty() {
+        println!("tags:     {}", e.tags.join(", "));
+    }
+    for a in &e.attachments {
+        println!("attached: {} {} ({} bytes)", a.id, a.file_name, a.size);
+    }
+    println!("created:  {}", e.created_at);
+    println!("updated:  {}", e.updated_at);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn apply_fields_only_touches_given_flags() {
+        let mut entry = VaultEntry::new("Mail", build_kind(Kind::Login, &EntryFields {
+            username: Some("alice".into()),
+            url: Some("https://mail".into()),
+            ..Default::default()
+        }, Some("pw".into())));
+
+        apply_fields(&mut entry, &EntryFields { url: Some(String::new()), ..Default::default() }, None);
+        match &entry.kind {
+            EntryKind::Login(l) => {
+                assert_eq!(l.username, "alice");
+                assert_eq!(l.password.as_deref(), Some("pw"));
+                assert!(l.url.is_none());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn flags_must_belong_to_the_entry_kind() {
+        let rename = EntryFields { file_name: Some("scan.pdf".into()), ..Default::default() };
+        assert!(check_flags("file", &rename).is_ok());
+        assert!(check_flags("login", &rename).is_err());
+        assert!(check_flags("file", &EntryFields { username: Some("a".into()), ..Default::default() }).is_err());
+        assert!(check_flags("note", &EntryFields { notes: Some("x".into()), tags: vec!["t".into()], ..Default::default() }).is_ok());
+        assert!(kind_of(&EntryKind::File(Default::default())).is_none());
+    }
+
+    #[test]
+    fn recovery_phrase_has_twelve_words() {
+        assert_eq!(generate_recovery_phrase().split_whitespace().count(), 12);
+    }
+}
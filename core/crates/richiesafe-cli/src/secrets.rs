@@ -0,0 +1,47 @@
+use std::io::{self, BufRead, IsTerminal};
+use zeroize::Zeroizing;
+
+/// Reads PINs, recovery phrases and entry secrets either from the TTY (no echo)
+/// or, for scripting, one per line from stdin.
+pub struct Secrets {
+    from_stdin: bool,
+}
+
+impl Secrets {
+    /// Falls back to stdin automatically when it is not a terminal.
+    pub fn new(force_stdin: bool) -> Self {
+        Self { from_stdin: force_stdin || !io::stdin().is_terminal() }
+    }
+
+    pub fn read(&mut self, prompt: &str) -> io::Result<Zeroizing<String>> {
+        if !self.from_stdin {
+            return rpassword::prompt_password(prompt).map(Zeroizing::new);
+        }
+
+        let mut line = Zeroizing::new(String::new());
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("stdin closed while reading: {}", prompt.trim_end_matches(": "))));
+        }
+        let trimmed = line.trim_end_matches(['\r', '\n']).len();
+        line.truncate(trimmed);
+        Ok(line)
+    }
+
+    /// Like `read`, but rejects empty input.
+    pub fn read_required(&mut self, prompt: &str) -> io::Result<Zeroizing<String>> {
+        let value = self.read(prompt)?;
+        if value.trim().is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty secret"));
+        }
+        Ok(value)
+    }
+
+    /// Reads a new secret; on a TTY it must be typed twice.
+    pub fn read_new(&mut self, prompt: &str, confirm_prompt: &str) -> io::Result<Zeroizing<String>> {
+        let value = self.read_required(prompt)?;
+        if !self.from_stdin && *self.read(confirm_prompt)? != *value {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "entries do not match"));
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,61 @@
+//! Runs the built binary against a scratch vault.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("richiesafe-cli-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn run(dir: &Path, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_richiesafe"))
+        .args(["--stdin", "--allow-weak-pin", "--vault"])
+        .arg(dir.join("vault.rsafe"))
+        .arg("--device-key")
+        .arg(dir.join("device.key"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn wrong_pins_lock_the_cli_out() {
+    let dir = TempDir::new();
+    assert!(run(&dir.0, &["init"], "1234\n").status.success());
+    assert!(run(&dir.0, &["unlock"], "1234\n").status.success());
+
+    // The default policy allows 3 failures, then delays the next attempt
+    for _ in 0..4 {
+        let output = run(&dir.0, &["unlock"], "0000\n");
+        assert!(!output.status.success());
+        assert!(stderr(&output).contains("Authentication failed"), "{}", stderr(&output));
+    }
+
+    let output = run(&dir.0, &["unlock"], "1234\n");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("retry in"), "{}", stderr(&output));
+    assert!(dir.0.join("vault.rsafe.attempts").exists());
+}
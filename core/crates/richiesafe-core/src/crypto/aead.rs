@@ -1,28 +1,104 @@
+use aes_gcm_siv::Aes256GcmSiv;
 use chacha20poly1305::{
     aead::{Aead, KeyInit, Payload},
     XChaCha20Poly1305, XNonce
 };
+use crate::error::{Result, RichieSafeError};
 
-pub fn encrypt(key: &[u8; 32], nonce: &[u8; 24], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+/// AEAD selected by the header's `aead_id`. Every format field holds a 24-byte
+/// nonce; AES-256-GCM-SIV uses its first 12 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AeadAlgorithm {
+    XChaCha20Poly1305 = 0x01,
+    Aes256GcmSiv = 0x02,
+}
+
+impl AeadAlgorithm {
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0x01 => Ok(Self::XChaCha20Poly1305),
+            0x02 => Ok(Self::Aes256GcmSiv),
+            _ => Err(RichieSafeError::UnsupportedAead(id)),
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn encrypt(self, key: &[u8; 32], nonce: &[u8; 24], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let payload = Payload { msg: plaintext, aad };
+        match self {
+            Self::XChaCha20Poly1305 => encrypt(key, nonce, plaintext, aad),
+            Self::Aes256GcmSiv => Aes256GcmSiv::new(key.into())
+                .encrypt(nonce[..12].into(), payload)
+                .map_err(|_| RichieSafeError::Encryption),
+        }
+    }
+
+    pub fn decrypt(self, key: &[u8; 32], nonce: &[u8; 24], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let payload = Payload { msg: ciphertext, aad };
+        match self {
+            Self::XChaCha20Poly1305 => decrypt(key, nonce, ciphertext, aad),
+            Self::Aes256GcmSiv => Aes256GcmSiv::new(key.into())
+                .decrypt(nonce[..12].into(), payload)
+                .map_err(|_| RichieSafeError::Decryption),
+        }
+    }
+}
+
+pub fn encrypt(key: &[u8; 32], nonce: &[u8; 24], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
     let cipher = XChaCha20Poly1305::new(key.into());
     let nonce = XNonce::from_slice(nonce);
     let payload = Payload {
         msg: plaintext,
-        aad: aad,
+        aad,
     };
     
     cipher.encrypt(nonce, payload)
-        .map_err(|e| e.to_string())
+        .map_err(|_| RichieSafeError::Encryption)
 }
 
-pub fn decrypt(key: &[u8; 32], nonce: &[u8; 24], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+pub fn decrypt(key: &[u8; 32], nonce: &[u8; 24], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
     let cipher = XChaCha20Poly1305::new(key.into());
     let nonce = XNonce::from_slice(nonce);
     let payload = Payload {
         msg: ciphertext,
-        aad: aad,
+        aad,
     };
 
     cipher.decrypt(nonce, payload)
-        .map_err(|e| e.to_string())
+        .map_err(|_| RichieSafeError::Decryption)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aes_gcm_siv_rfc8452_vector() {
+        // RFC 8452, C.2: AES-256-GCM-SIV with empty plaintext and AAD
+        let mut key = [0u8; 32];
+        key[0] = 0x01;
+        let mut nonce = [0u8; 24];
+        nonce[0] = 0x03;
+        let tag = AeadAlgorithm::Aes256GcmSiv.encrypt(&key, &nonce, b"", b"").unwrap();
+        assert_eq!(hex::encode(tag), "07f5f4169bbf55a8400cd47ea6fd400f");
+    }
+
+    #[test]
+    fn test_algorithms_roundtrip_and_differ() {
+        let key = [5u8; 32];
+        let nonce = [6u8; 24];
+        let mut outputs = Vec::new();
+        for alg in [AeadAlgorithm::XChaCha20Poly1305, AeadAlgorithm::Aes256GcmSiv] {
+            assert_eq!(AeadAlgorithm::from_id(alg.id()), Ok(alg));
+            let ct = alg.encrypt(&key, &nonce, b"secret", b"aad").unwrap();
+            assert_eq!(alg.decrypt(&key, &nonce, &ct, b"aad").unwrap(), b"secret");
+            assert_eq!(alg.decrypt(&key, &nonce, &ct, b"other"), Err(RichieSafeError::Decryption));
+            outputs.push(ct);
+        }
+        assert_ne!(outputs[0], outputs[1]);
+        assert_eq!(AeadAlgorithm::from_id(0), Err(RichieSafeError::UnsupportedAead(0)));
+    }
 }
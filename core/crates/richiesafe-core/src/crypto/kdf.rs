@@ -1,7 +1,44 @@
 use argon2::{
     Argon2, Params, Algorithm, Version
 };
-use zeroize::Zeroize;
+use crate::error::{Result, RichieSafeError};
+use std::sync::RwLock;
+
+/// Calibration never returns less than this (OWASP minimum for Argon2id).
+pub const MIN_M_COST: u32 = 19 * 1024; // 19 MiB
+pub const MIN_T_COST: u32 = 2;
+/// Upper bound on passes so a fast device with a long target stays reasonable.
+pub const MAX_CALIBRATED_T_COST: u32 = 16;
+
+/// Unlock time aimed for by `calibrate` when callers have no preference.
+pub const DEFAULT_TARGET_MS: u32 = 500;
+/// Memory ceiling for `calibrate` on desktop and mobile.
+pub const DEFAULT_MAX_MEMORY_KIB: u32 = 256 * 1024;
+
+/// KDF selected by the header's `kdf_id`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KdfAlgorithm {
+    Argon2id = 0x01,
+}
+
+impl KdfAlgorithm {
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0x01 => Ok(Self::Argon2id),
+            _ => Err(RichieSafeError::UnsupportedKdf(id)),
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn derive_key(self, secret: &[u8], salt: &[u8], params: KdfParams) -> Result<[u8; 32]> {
+        match self {
+            Self::Argon2id => derive_key(secret, salt, params),
+        }
+    }
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct KdfParams {
@@ -10,6 +47,12 @@ pub struct KdfParams {
     pub p_cost: u32,
 }
 
+impl KdfParams {
+    /// The weakest parameters `calibrate` will return.
+    pub const FLOOR: KdfParams = KdfParams { m_cost: MIN_M_COST, t_cost: MIN_T_COST, p_cost: 1 };
+}
+
+/// Fixed reference cost. Vault creation and PIN changes use `calibrate` instead.
 impl Default for KdfParams {
     fn default() -> Self {
         Self {
@@ -20,13 +63,74 @@ impl Default for KdfParams {
     }
 }
 
-pub fn derive_key(secret: &[u8], salt: &[u8], params: KdfParams) -> Result<[u8; 32], String> {
+/// Bounds on Argon2id parameters. Header parameters come from an untrusted blob,
+/// so they are checked against the active policy before any derivation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfPolicy {
+    pub min_m_cost: u32,
+    pub max_m_cost: u32,
+    pub min_t_cost: u32,
+    pub max_t_cost: u32,
+    pub min_p_cost: u32,
+    pub max_p_cost: u32,
+}
+
+impl KdfPolicy {
+    /// Desktop and mobile default: the calibration floor up to 1 GiB and 64 passes.
+    pub const NATIVE: KdfPolicy = KdfPolicy {
+        min_m_cost: MIN_M_COST,
+        max_m_cost: 1024 * 1024,
+        min_t_cost: MIN_T_COST,
+        max_t_cost: 64,
+        min_p_cost: 1,
+        max_p_cost: 16,
+    };
+
+    pub fn check(&self, params: &KdfParams) -> Result<()> {
+        let out_of_range = |name: &str, value: u32, min: u32, max: u32| {
+            RichieSafeError::KdfOutOfPolicy(format!("{name} = {value} outside {min}..={max}"))
+        };
+        if !(self.min_m_cost..=self.max_m_cost).contains(&params.m_cost) {
+            return Err(out_of_range("m_cost", params.m_cost, self.min_m_cost, self.max_m_cost));
+        }
+        if !(self.min_t_cost..=self.max_t_cost).contains(&params.t_cost) {
+            return Err(out_of_range("t_cost", params.t_cost, self.min_t_cost, self.max_t_cost));
+        }
+        if !(self.min_p_cost..=self.max_p_cost).contains(&params.p_cost) {
+            return Err(out_of_range("p_cost", params.p_cost, self.min_p_cost, self.max_p_cost));
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfPolicy {
+    fn default() -> Self {
+        Self::NATIVE
+    }
+}
+
+static POLICY: RwLock<KdfPolicy> = RwLock::new(KdfPolicy::NATIVE);
+
+/// Replaces the process-wide policy. Platform layers call this once at startup,
+/// e.g. the wasm build lowers the memory ceiling.
+pub fn set_policy(policy: KdfPolicy) {
+    *POLICY.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = policy;
+}
+
+pub fn policy() -> KdfPolicy {
+    *POLICY.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Fails with `KdfOutOfPolicy` before doing any work if `params` are outside `policy()`.
+pub fn derive_key(secret: &[u8], salt: &[u8], params: KdfParams) -> Result<[u8; 32]> {
+    policy().check(&params)?;
+
     let argon_params = Params::new(
         params.m_cost,
         params.t_cost,
         params.p_cost,
         Some(32) // Output length
-    ).map_err(|e| e.to_string())?;
+    ).map_err(|e| RichieSafeError::Kdf(e.to_string()))?;
 
     let argon2 = Argon2::new(
         Algorithm::Argon2id,
@@ -36,7 +140,116 @@ pub fn derive_key(secret: &[u8], salt: &[u8], params: KdfParams) -> Result<[u8;
 
     let mut output = [0u8; 32];
     argon2.hash_password_into(secret, salt, &mut output)
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| RichieSafeError::Kdf(e.to_string()))?;
 
     Ok(output)
 }
+
+/// Benchmarks Argon2id on this device and picks parameters that take about
+/// `target_ms` per unlock, using as much memory as allowed up to `max_memory_kib`.
+/// The result is never below `MIN_M_COST` / `MIN_T_COST` and always within `policy()`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn calibrate(target_ms: u32, max_memory_kib: u32) -> Result<KdfParams> {
+    calibrate_with(target_ms, max_memory_kib, |params| {
+        let start = std::time::Instant::now();
+        derive_key(b"calibration", &[0u8; 16], params)?;
+        Ok(start.elapsed().as_secs_f64() * 1000.0)
+    })
+}
+
+/// `calibrate` with the timing supplied by the caller, for targets without
+/// `std::time::Instant` (wasm). `measure` runs one derivation and returns its
+/// duration in milliseconds.
+pub fn calibrate_with<F>(target_ms: u32, max_memory_kib: u32, mut measure: F) -> Result<KdfParams>
+where
+    F: FnMut(KdfParams) -> Result<f64>,
+{
+    let policy = policy();
+    let target = f64::from(target_ms);
+    let floor = MIN_M_COST.max(policy.min_m_cost);
+    let max_t_cost = MAX_CALIBRATED_T_COST.min(policy.max_t_cost);
+    // Measured at MIN_T_COST passes so every trial run is itself within policy
+    let mut params = KdfParams {
+        m_cost: max_memory_kib.min(policy.max_m_cost).max(floor),
+        t_cost: MIN_T_COST,
+        p_cost: 1,
+    };
+
+    // Prefer memory over passes: shrink memory only until MIN_T_COST passes fit the target.
+    let per_pass = loop {
+        let ms = measure(params)?.max(0.001);
+        if ms <= target || params.m_cost == floor {
+            break ms / f64::from(MIN_T_COST);
+        }
+        params.m_cost = (params.m_cost / 2).max(floor);
+    };
+
+    params.t_cost = ((target / per_pass) as u32).clamp(MIN_T_COST, max_t_cost);
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Simulated device where one pass costs `ms_per_mib` per MiB.
+    fn device(ms_per_mib: f64) -> impl FnMut(KdfParams) -> Result<f64> {
+        move |p| Ok(f64::from(p.m_cost) / 1024.0 * ms_per_mib * f64::from(p.t_cost))
+    }
+
+    #[test]
+    fn test_calibrate_prefers_memory_then_passes() {
+        // Fast device: 256 MiB pass = 64 ms, so 500 ms buys 7 passes at full memory
+        let p = calibrate_with(500, 256 * 1024, device(0.25)).unwrap();
+        assert_eq!((p.m_cost, p.t_cost, p.p_cost), (256 * 1024, 7, 1));
+
+        // Slow device: memory is halved until two passes fit
+        let p = calibrate_with(500, 256 * 1024, device(4.0)).unwrap();
+        assert_eq!((p.m_cost, p.t_cost), (32 * 1024, 3));
+    }
+
+    #[test]
+    fn test_calibrate_never_goes_below_floor() {
+        // Even hopeless devices and tiny targets get at least the floor
+        let p = calibrate_with(1, 1024, device(1000.0)).unwrap();
+        assert_eq!((p.m_cost, p.t_cost), (MIN_M_COST, MIN_T_COST));
+
+        let p = calibrate_with(60_000, 256 * 1024, device(0.001)).unwrap();
+        assert_eq!(p.t_cost, MAX_CALIBRATED_T_COST);
+    }
+
+    #[test]
+    fn test_policy_bounds() {
+        let policy = KdfPolicy::NATIVE;
+        assert!(policy.check(&KdfParams::FLOOR).is_ok());
+        assert!(policy.check(&KdfParams::default()).is_ok());
+
+        let over = [
+            KdfParams { m_cost: 4 * 1024 * 1024, ..KdfParams::FLOOR },
+            KdfParams { m_cost: 8, t_cost: 1, p_cost: 1 },
+            KdfParams { t_cost: 0, ..KdfParams::FLOOR },
+            KdfParams { t_cost: 1_000_000, ..KdfParams::FLOOR },
+            KdfParams { p_cost: 0, ..KdfParams::FLOOR },
+            KdfParams { p_cost: 255, ..KdfParams::FLOOR },
+        ];
+        for params in over {
+            assert!(matches!(policy.check(&params), Err(RichieSafeError::KdfOutOfPolicy(_))), "{params:?}");
+            // Rejected before Argon2 allocates anything
+            assert!(matches!(derive_key(b"pw", &[0u8; 16], params), Err(RichieSafeError::KdfOutOfPolicy(_))));
+        }
+    }
+
+    #[test]
+    fn test_calibrate_stays_within_policy() {
+        // Memory above the policy ceiling is never tried
+        let mut tried = Vec::new();
+        let p = calibrate_with(500, u32::MAX, |params| {
+            tried.push(params);
+            Ok(0.0)
+        })
+        .unwrap();
+        assert!(tried.iter().all(|t| KdfPolicy::NATIVE.check(t).is_ok()));
+        assert!(KdfPolicy::NATIVE.check(&p).is_ok());
+        assert_eq!(p.m_cost, KdfPolicy::NATIVE.max_m_cost);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod kdf;
 pub mod aead;
 pub mod rng;
+pub mod subkey;
+pub mod suite;
@@ -10,3 +10,78 @@ pub fn generate_bytes(length: usize) -> Vec<u8> {
 pub fn fill_bytes(buffer: &mut [u8]) {
     OsRng.fill_bytes(buffer);
 }
+
+/// Uniform integer in `0..bound` without modulo bias.
+pub fn uniform(bound: u32) -> u32 {
+    uniform_with(&mut OsRng, bound)
+}
+
+/// [`uniform`] drawing from `rng`. Draws below `2^32 mod bound` would make the
+/// low residues more likely and are redrawn.
+pub fn uniform_with<R: RngCore + ?Sized>(rng: &mut R, bound: u32) -> u32 {
+    assert!(bound > 0, "uniform bound must be positive");
+    let reject_below = bound.wrapping_neg() % bound;
+    loop {
+        let x = rng.next_u32();
+        if x >= reject_below {
+            return x % bound;
+        }
+    }
+}
+
+/// Fisher-Yates shuffle using [`uniform`].
+pub fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = uniform(i as u32 + 1) as usize;
+        items.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Replays fixed outputs; wider draws are built from the same script.
+    struct Scripted(Vec<u32>);
+
+    impl RngCore for Scripted {
+        fn next_u32(&mut self) -> u32 {
+            self.0.remove(0)
+        }
+        fn next_u64(&mut self) -> u64 {
+            let low = self.next_u32() as u64;
+            (self.next_u32() as u64) << 32 | low
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(4) {
+                chunk.copy_from_slice(&self.next_u32().to_le_bytes()[..chunk.len()]);
+            }
+        }
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_uniform_rejects_the_biased_zone() {
+        // 2^32 = 1 * bound + 2^30: the first 2^30 values would double-count 0..2^30
+        let bound = 3 << 30;
+        let mut rng = Scripted(vec![0, (1 << 30) - 1, 1 << 30, 7, bound + 7]);
+        assert_eq!(uniform_with(&mut rng, bound), 1 << 30);
+        assert_eq!(uniform_with(&mut rng, bound), 7);
+        assert_eq!(uniform_with(&mut Scripted(vec![u32::MAX]), 1), 0);
+    }
+
+    #[test]
+    fn test_uniform_is_flat() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut counts = [0u32; 6];
+        for _ in 0..60_000 {
+            counts[uniform_with(&mut rng, 6) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&c| (9_500..=10_500).contains(&c)), "{counts:?}");
+    }
+}
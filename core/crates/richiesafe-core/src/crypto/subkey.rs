@@ -0,0 +1,12 @@
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// Derives an independent 32-byte key from the vault key with HKDF-SHA256.
+/// `info` must be unique per purpose (and per object, e.g. an attachment id).
+pub fn derive_subkey(vault_key: &[u8; 32], info: &[u8]) -> [u8; 32] {
+    let hk = Hkdf::<Sha256>::new(None, vault_key);
+    let mut out = [0u8; 32];
+    // 32 bytes is always a valid HKDF-SHA256 output length
+    hk.expand(info, &mut out).expect("valid HKDF length");
+    out
+}
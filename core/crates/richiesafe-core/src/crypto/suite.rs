@@ -0,0 +1,39 @@
+//! Algorithm dispatch by the `kdf_id` / `aead_id` stored in the vault header.
+
+use crate::crypto::aead::AeadAlgorithm;
+use crate::crypto::kdf::{KdfAlgorithm, KdfParams};
+use crate::error::Result;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CipherSuite {
+    pub kdf: KdfAlgorithm,
+    pub aead: AeadAlgorithm,
+}
+
+impl CipherSuite {
+    /// What new vaults use unless told otherwise.
+    pub const DEFAULT: CipherSuite = CipherSuite {
+        kdf: KdfAlgorithm::Argon2id,
+        aead: AeadAlgorithm::XChaCha20Poly1305,
+    };
+
+    /// Fails with `UnsupportedKdf` / `UnsupportedAead` for ids this build does not know.
+    pub fn from_ids(kdf_id: u8, aead_id: u8) -> Result<Self> {
+        Ok(Self {
+            kdf: KdfAlgorithm::from_id(kdf_id)?,
+            aead: AeadAlgorithm::from_id(aead_id)?,
+        })
+    }
+
+    pub fn derive_key(&self, secret: &[u8], salt: &[u8], params: KdfParams) -> Result<[u8; 32]> {
+        self.kdf.derive_key(secret, salt, params)
+    }
+
+    pub fn encrypt(&self, key: &[u8; 32], nonce: &[u8; 24], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.aead.encrypt(key, nonce, plaintext, aad)
+    }
+
+    pub fn decrypt(&self, key: &[u8; 32], nonce: &[u8; 24], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.aead.decrypt(key, nonce, ciphertext, aad)
+    }
+}
@@ -0,0 +1,154 @@
+use thiserror::Error;
+use uuid::Uuid;
+
+pub type Result<T> = std::result::Result<T, RichieSafeError>;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum RichieSafeError {
+    #[error("Authentication failed")]
+    WrongSecret,
+    #[error("Corrupted header: {0}")]
+    CorruptedHeader(&'static str),
+    #[error("Unsupported version: {0}")]
+    UnsupportedVersion(u16),
+    #[error("Blob truncated")]
+    TruncatedBlob,
+    #[error("Body decryption failed")]
+    BodyDecryption,
+    #[error("CBOR decode failed: {0}")]
+    CborDecode(String),
+    #[error("CBOR encode failed: {0}")]
+    CborEncode(String),
+    #[error("Invalid KDF parameters: {0}")]
+    Kdf(String),
+    #[error("Encryption failed")]
+    Encryption,
+    #[error("Decryption failed")]
+    Decryption,
+    #[error("Missing unlock method: {0}")]
+    MissingUnlockMethod(&'static str),
+    #[error("Entry not found: {0}")]
+    EntryNotFound(Uuid),
+    #[error("Unsupported schema version: {0}")]
+    UnsupportedSchema(u16),
+    #[error("Attachment not found: {0}")]
+    AttachmentNotFound(Uuid),
+    #[error("Attachment decryption failed")]
+    AttachmentDecryption,
+    #[error("Current PIN verification failed")]
+    PinVerificationFailed,
+    #[error("Vault file is incomplete or damaged (torn write)")]
+    TornWrite,
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("KDF parameters outside policy: {0}")]
+    KdfOutOfPolicy(String),
+    #[error("Vault header has been tampered with")]
+    HeaderTampered,
+    #[error("Unsupported KDF id: {0}")]
+    UnsupportedKdf(u8),
+    #[error("Unsupported AEAD id: {0}")]
+    UnsupportedAead(u8),
+    #[error("Too many failed attempts, retry in {0} s")]
+    Throttled(u64),
+    #[error("Vault locked out after too many failed attempts")]
+    LockedOut,
+    #[error("Too many failed attempts, recovery phrase required")]
+    RecoveryRequired,
+    #[error("Attempt record is invalid or belongs to another vault")]
+    AttemptRecordTampered,
+    #[error("Secret too weak: {0}")]
+    WeakSecret(String),
+    #[error("Invalid generator options: {0}")]
+    InvalidGeneratorOptions(String),
+    #[error("Invalid OTP seed: {0}")]
+    InvalidOtp(String),
+    #[error("Entry has no OTP seed: {0}")]
+    OtpNotConfigured(Uuid),
+    #[error("Invalid custom field: {0}")]
+    InvalidField(String),
+}
+
+impl RichieSafeError {
+    /// Stable numeric code exposed to the wasm and ffi layers.
+    /// Codes are never reused; new variants get new numbers.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::WrongSecret => 1,
+            Self::CorruptedHeader(_) => 2,
+            Self::UnsupportedVersion(_) => 3,
+            Self::TruncatedBlob => 4,
+            Self::BodyDecryption => 5,
+            Self::CborDecode(_) => 6,
+            Self::CborEncode(_) => 7,
+            Self::Kdf(_) => 8,
+            Self::Encryption => 9,
+            Self::Decryption => 10,
+            Self::MissingUnlockMethod(_) => 11,
+            Self::EntryNotFound(_) => 12,
+            Self::UnsupportedSchema(_) => 13,
+            Self::AttachmentNotFound(_) => 14,
+            Self::AttachmentDecryption => 15,
+            Self::PinVerificationFailed => 16,
+            Self::TornWrite => 17,
+            Self::Io(_) => 18,
+            Self::KdfOutOfPolicy(_) => 19,
+            Self::HeaderTampered => 20,
+            Self::UnsupportedKdf(_) => 21,
+            Self::UnsupportedAead(_) => 22,
+            Self::Throttled(_) => 23,
+            Self::LockedOut => 24,
+            Self::RecoveryRequired => 25,
+            Self::AttemptRecordTampered => 26,
+            Self::WeakSecret(_) => 27,
+            Self::InvalidGeneratorOptions(_) => 28,
+            Self::InvalidOtp(_) => 29,
+            Self::OtpNotConfigured(_) => 30,
+            Self::InvalidField(_) => 31,
+        }
+    }
+
+    /// Stable symbolic name for `code()`, used as the JS error name.
+    pub fn code_name(&self) -> &'static str {
+        match self {
+            Self::WrongSecret => "WRONG_SECRET",
+            Self::CorruptedHeader(_) => "CORRUPTED_HEADER",
+            Self::UnsupportedVersion(_) => "UNSUPPORTED_VERSION",
+            Self::TruncatedBlob => "TRUNCATED_BLOB",
+            Self::BodyDecryption => "BODY_DECRYPTION",
+            Self::CborDecode(_) => "CBOR_DECODE",
+            Self::CborEncode(_) => "CBOR_ENCODE",
+            Self::Kdf(_) => "KDF",
+            Self::Encryption => "ENCRYPTION",
+            Self::Decryption => "DECRYPTION",
+            Self::MissingUnlockMethod(_) => "MISSING_UNLOCK_METHOD",
+            Self::EntryNotFound(_) => "ENTRY_NOT_FOUND",
+            Self::UnsupportedSchema(_) => "UNSUPPORTED_SCHEMA",
+            Self::AttachmentNotFound(_) => "ATTACHMENT_NOT_FOUND",
+            Self::AttachmentDecryption => "ATTACHMENT_DECRYPTION",
+            Self::PinVerificationFailed => "PIN_VERIFICATION_FAILED",
+            Self::TornWrite => "TORN_WRITE",
+            Self::Io(_) => "IO",
+            Self::KdfOutOfPolicy(_) => "KDF_OUT_OF_POLICY",
+            Self::HeaderTampered => "HEADER_TAMPERED",
+            Self::UnsupportedKdf(_) => "UNSUPPORTED_KDF",
+            Self::UnsupportedAead(_) => "UNSUPPORTED_AEAD",
+            Self::Throttled(_) => "THROTTLED",
+            Self::LockedOut => "LOCKED_OUT",
+            Self::RecoveryRequired => "RECOVERY_REQUIRED",
+            Self::AttemptRecordTampered => "ATTEMPT_RECORD_TAMPERED",
+            Self::WeakSecret(_) => "WEAK_SECRET",
+            Self::InvalidGeneratorOptions(_) => "INVALID_GENERATOR_OPTIONS",
+            Self::InvalidOtp(_) => "INVALID_OTP",
+            Self::OtpNotConfigured(_) => "OTP_NOT_CONFIGURED",
+            Self::InvalidField(_) => "INVALID_FIELD",
+        }
+    }
+}
+
+#[cfg(feature = "std-fs")]
+impl From<std::io::Error> for RichieSafeError {
+    fn from(e: std::io::Error) -> Self {
+        RichieSafeError::Io(e.to_string())
+    }
+}
@@ -0,0 +1,243 @@
+//! Password and passphrase generation. Every draw goes through
+//! `crypto::rng::uniform`, so each allowed character (or word) is equally likely.
+
+use crate::crypto::rng;
+use crate::error::{Result, RichieSafeError};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()-_=+[]{};:,.<>/?~";
+/// Characters that are easily mistaken for one another when read or typed
+const AMBIGUOUS: &str = "0Oo1lI|`'\"";
+pub const MAX_PASSWORD_LENGTH: usize = 256;
+pub const MAX_PASSPHRASE_WORDS: usize = 64;
+
+/// Options for [`generate_password`]. Unset fields take their defaults when
+/// deserialized, so hosts can pass a partial object.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordOptions {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// Leave out `0 O o 1 l I |` and quotes
+    pub exclude_ambiguous: bool,
+    /// At least one character of every enabled class
+    pub require_each_class: bool,
+    /// Site rule: characters the site rejects
+    pub exclude_chars: String,
+    /// Site rule: the only symbols the site accepts (replaces the default set).
+    /// Letters and digits are rejected; they belong to their own classes.
+    pub symbol_set: Option<String>,
+}
+
+impl Default for PasswordOptions {
+    fn default() -> Self {
+        PasswordOptions {
+            length: 20,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: false,
+            require_each_class: true,
+            exclude_chars: String::new(),
+            symbol_set: None,
+        }
+    }
+}
+
+impl PasswordOptions {
+    /// The enabled classes after exclusions; a class left empty is an error.
+    /// Classes never share a character, so the concatenated pool stays uniform.
+    fn alphabets(&self) -> Result<Vec<Vec<char>>> {
+        let symbols = self.symbol_set.as_deref().unwrap_or(SYMBOLS);
+        if symbols.chars().any(|c| c.is_ascii_alphanumeric()) {
+            return Err(invalid("symbol_set may not contain letters or digits"));
+        }
+        let classes = [
+            (self.lowercase, LOWERCASE),
+            (self.uppercase, UPPERCASE),
+            (self.digits, DIGITS),
+            (self.symbols, symbols),
+        ];
+
+        let mut alphabets = Vec::new();
+        for (_, chars) in classes.into_iter().filter(|(enabled, _)| *enabled) {
+            let mut alphabet: Vec<char> = chars.chars()
+                .filter(|c| !(self.exclude_ambiguous && AMBIGUOUS.contains(*c)))
+                .filter(|c| !self.exclude_chars.contains(*c))
+                .collect();
+            alphabet.sort_unstable();
+            alphabet.dedup();
+            if alphabet.is_empty() {
+                return Err(invalid("a character class has no characters left after exclusions"));
+            }
+            alphabets.push(alphabet);
+        }
+        if alphabets.is_empty() {
+            return Err(invalid("no character class enabled"));
+        }
+        Ok(alphabets)
+    }
+
+    /// Entropy of a password generated with these options, ignoring the small
+    /// loss from `require_each_class`.
+    pub fn entropy_bits(&self) -> Result<f64> {
+        let size: usize = self.alphabets()?.iter().map(Vec::len).sum();
+        Ok(self.length as f64 * (size as f64).log2())
+    }
+}
+
+/// Options for [`generate_passphrase`]. Words come from the BIP39 English list
+/// (2048 words, 11 bits each).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    /// Upper-case the first letter of every word
+    pub capitalize: bool,
+    /// Append one random digit to one random word
+    pub include_number: bool,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        PassphraseOptions { words: 6, separator: "-".to_string(), capitalize: false, include_number: false }
+    }
+}
+
+fn invalid(reason: &str) -> RichieSafeError {
+    RichieSafeError::InvalidGeneratorOptions(reason.to_string())
+}
+
+fn pick<T: Copy>(items: &[T]) -> T {
+    items[rng::uniform(items.len() as u32) as usize]
+}
+
+pub fn generate_password(options: &PasswordOptions) -> Result<Zeroizing<String>> {
+    if options.length == 0 || options.length > MAX_PASSWORD_LENGTH {
+        return Err(invalid("length out of range"));
+    }
+    let alphabets = options.alphabets()?;
+    if options.require_each_class && options.length < alphabets.len() {
+        return Err(invalid("length is shorter than the number of required classes"));
+    }
+    let all: Vec<char> = alphabets.concat();
+
+    let mut chars = Zeroizing::new(Vec::with_capacity(options.length));
+    if options.require_each_class {
+        chars.extend(alphabets.iter().map(|a| pick(a)));
+    }
+    while chars.len() < options.length {
+        chars.push(pick(&all));
+    }
+    // The required characters must not always lead
+    rng::shuffle(&mut chars);
+
+    Ok(Zeroizing::new(chars.iter().collect()))
+}
+
+pub fn generate_passphrase(options: &PassphraseOptions) -> Result<Zeroizing<String>> {
+    if options.words == 0 || options.words > MAX_PASSPHRASE_WORDS {
+        return Err(invalid("word count out of range"));
+    }
+    let list = bip39::Language::English.word_list();
+
+    let mut words: Vec<Zeroizing<String>> = (0..options.words)
+        .map(|_| {
+            let word = pick(list);
+            Zeroizing::new(match options.capitalize {
+                true => word[..1].to_uppercase() + &word[1..],
+                false => word.to_string(),
+            })
+        })
+        .collect();
+    if options.include_number {
+        let i = rng::uniform(words.len() as u32) as usize;
+        words[i].push(char::from(b'0' + rng::uniform(10) as u8));
+    }
+
+    let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+    Ok(Zeroizing::new(words.join(&options.separator)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_classes_and_rules() {
+        let options = PasswordOptions { length: 4, ..Default::default() };
+        for _ in 0..200 {
+            let pw = generate_password(&options).unwrap();
+            assert_eq!(pw.chars().count(), 4);
+            assert!(pw.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(pw.chars().any(|c| c.is_ascii_uppercase()));
+            assert!(pw.chars().any(|c| c.is_ascii_digit()));
+            assert!(pw.chars().any(|c| SYMBOLS.contains(c)));
+        }
+
+        let site = PasswordOptions {
+            length: 64,
+            exclude_ambiguous: true,
+            exclude_chars: "xyz".into(),
+            symbol_set: Some("_!".into()),
+            ..Default::default()
+        };
+        let pw = generate_password(&site).unwrap();
+        assert!(pw.chars().all(|c| c.is_ascii_alphanumeric() || "_!".contains(c)));
+        assert!(!pw.chars().any(|c| AMBIGUOUS.contains(c) || "xyz".contains(c)));
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let none = PasswordOptions { lowercase: false, uppercase: false, digits: false, symbols: false, ..Default::default() };
+        assert!(matches!(generate_password(&none), Err(RichieSafeError::InvalidGeneratorOptions(_))));
+        let emptied = PasswordOptions { symbol_set: Some("|".into()), exclude_ambiguous: true, ..Default::default() };
+        assert!(generate_password(&emptied).is_err());
+        // Overlapping a letter or digit class would make those characters more likely
+        let overlapping = PasswordOptions { symbol_set: Some("a1_".into()), ..Default::default() };
+        assert!(matches!(generate_password(&overlapping), Err(RichieSafeError::InvalidGeneratorOptions(_))));
+        assert!(overlapping.entropy_bits().is_err());
+        assert!(generate_password(&PasswordOptions { length: 3, ..Default::default() }).is_err());
+        assert!(generate_password(&PasswordOptions { length: 0, require_each_class: false, ..Default::default() }).is_err());
+        assert!(generate_passphrase(&PassphraseOptions { words: 0, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_characters_are_equally_likely() {
+        let options = PasswordOptions { length: 200, uppercase: false, symbols: false, require_each_class: false, ..Default::default() };
+        let mut counts = [0u32; 36];
+        for _ in 0..180 {
+            for c in generate_password(&options).unwrap().chars() {
+                counts[c.to_digit(36).unwrap() as usize] += 1;
+            }
+        }
+        // 1000 expected per character; 5 sigma is about 155
+        assert!(counts.iter().all(|&c| (845..=1155).contains(&c)), "{counts:?}");
+        assert!((options.entropy_bits().unwrap() - 200.0 * 36f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_passphrase() {
+        let list = bip39::Language::English.word_list();
+        let phrase = generate_passphrase(&PassphraseOptions::default()).unwrap();
+        let words: Vec<&str> = phrase.split('-').collect();
+        assert_eq!(words.len(), 6);
+        assert!(words.iter().all(|w| list.contains(w)));
+
+        let options = PassphraseOptions { words: 4, separator: " ".into(), capitalize: true, include_number: true };
+        let phrase = generate_passphrase(&options).unwrap();
+        let words: Vec<&str> = phrase.split(' ').collect();
+        assert_eq!(words.len(), 4);
+        assert!(words.iter().all(|w| w.starts_with(|c: char| c.is_ascii_uppercase())));
+        assert_eq!(phrase.chars().filter(char::is_ascii_digit).count(), 1);
+    }
+}
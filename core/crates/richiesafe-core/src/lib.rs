@@ -1,4 +1,12 @@
 pub mod crypto;
+pub mod error;
+pub mod generator;
 pub mod vault;
 pub mod models;
+pub mod otp;
+pub mod strength;
 pub mod util;
+#[cfg(feature = "std-fs")]
+pub mod storage;
+
+pub use error::{Result, RichieSafeError};
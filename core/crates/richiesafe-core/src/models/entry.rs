@@ -2,23 +2,198 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use zeroize::{Zeroize, ZeroizeOnDrop};
+use crate::error::{Result, RichieSafeError};
+use crate::otp::OtpSecret;
+
+/// Current `VaultState::schema_version`. Older bodies are upgraded by `models::migration`.
+pub const SCHEMA_VERSION: u16 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct LoginData {
+    pub username: String,
+    pub password: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct CardData {
+    pub holder: Option<String>,
+    pub number: String,
+    /// Free-form as typed by the user, usually `MM/YY`.
+    pub expiry: Option<String>,
+    pub cvv: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct NoteData {
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct IdentityData {
+    pub full_name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub birth_date: Option<String>,
+    pub document_number: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct FileData {
+    pub file_name: String,
+    pub mime_type: Option<String>,
+    /// Inline content kept in the vault body. Empty once moved into an
+    /// attachment by `VaultHandle::externalize_files`.
+    #[serde(with = "serde_bytes")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Text,
+    /// Masked in listings; the value is only handed out by an explicit getter
+    Hidden,
+    Url,
+    Email,
+    /// `YYYY-MM-DD`
+    Date,
+    /// `true` or `false`
+    Boolean,
+}
+
+impl FieldType {
+    /// Stable lowercase name, matching the serialized form.
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldType::Text => "text",
+            FieldType::Hidden => "hidden",
+            FieldType::Url => "url",
+            FieldType::Email => "email",
+            FieldType::Date => "date",
+            FieldType::Boolean => "boolean",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Text, Self::Hidden, Self::Url, Self::Email, Self::Date, Self::Boolean]
+            .into_iter()
+            .find(|t| t.name() == name)
+    }
+}
+
+/// A user-defined field (security question, account number, API key, ...).
+/// Entries keep them in the order the user arranged them.
+#[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct CustomField {
+    pub name: String,
+    pub value: String,
+    #[serde(rename = "type")]
+    #[zeroize(skip)]
+    pub field_type: FieldType,
+}
+
+impl CustomField {
+    /// Checks that `value` fits `field_type`; hosts should build fields through this.
+    pub fn new(name: &str, field_type: FieldType, value: String) -> Result<Self> {
+        let field = CustomField { name: name.trim().to_string(), value, field_type };
+        field.validate()?;
+        Ok(field)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| Err(RichieSafeError::InvalidField(format!("{}: {reason}", self.name)));
+        if self.name.is_empty() {
+            return Err(RichieSafeError::InvalidField("empty field name".to_string()));
+        }
+        match self.field_type {
+            FieldType::Email if !self.value.is_empty() && !self.value.contains('@') => invalid("not an email address"),
+            FieldType::Date if !self.value.is_empty()
+                && chrono::NaiveDate::parse_from_str(&self.value, "%Y-%m-%d").is_err() => invalid("expected YYYY-MM-DD"),
+            FieldType::Boolean if !matches!(self.value.as_str(), "true" | "false") => invalid("expected true or false"),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.field_type == FieldType::Hidden
+    }
+}
+
+/// Reference to an attachment blob stored outside the vault body (see `vault::attachment`).
+#[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct AttachmentRef {
+    #[zeroize(skip)]
+    pub id: Uuid,
+    pub file_name: String,
+    pub mime_type: Option<String>,
+    #[zeroize(skip)]
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntryKind {
+    Login(LoginData),
+    Card(CardData),
+    Note(NoteData),
+    Identity(IdentityData),
+    File(FileData),
+}
+
+impl EntryKind {
+    /// Stable lowercase name, matching the serialized tag.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryKind::Login(_) => "login",
+            EntryKind::Card(_) => "card",
+            EntryKind::Note(_) => "note",
+            EntryKind::Identity(_) => "identity",
+            EntryKind::File(_) => "file",
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct VaultEntry {
     #[zeroize(skip)]
     pub id: Uuid,
     pub title: String,
-    pub username: String,
-    pub password: Option<String>,
-    pub url: Option<String>,
+    pub kind: EntryKind,
     pub notes: Option<String>,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub fields: Vec<CustomField>,
+    #[serde(default)]
+    pub attachments: Vec<AttachmentRef>,
+    /// Two-factor seed shown as a one-time code next to the entry
+    #[serde(default)]
+    pub otp: Option<OtpSecret>,
     #[zeroize(skip)]
     pub created_at: DateTime<Utc>,
     #[zeroize(skip)]
     pub updated_at: DateTime<Utc>,
 }
 
+impl VaultEntry {
+    pub fn new(title: &str, kind: EntryKind) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            kind,
+            notes: None,
+            tags: Vec::new(),
+            fields: Vec::new(),
+            attachments: Vec::new(),
+            otp: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct VaultState {
     #[zeroize(skip)]
@@ -33,10 +208,57 @@ pub struct VaultState {
 impl VaultState {
     pub fn new() -> Self {
         Self {
-            schema_version: 1,
+            schema_version: SCHEMA_VERSION,
             vault_uuid: Uuid::new_v4(),
             entries: Vec::new(),
             created_at: Utc::now(),
         }
     }
 }
+
+impl Default for VaultState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_field_validation() {
+        assert!(CustomField::new("Born", FieldType::Date, "1990-04-12".into()).is_ok());
+        assert!(CustomField::new("Born", FieldType::Date, "12/04/1990".into()).is_err());
+        assert!(CustomField::new("Admin", FieldType::Boolean, "yes".into()).is_err());
+        assert!(CustomField::new("Contact", FieldType::Email, "nobody".into()).is_err());
+        assert!(CustomField::new("  ", FieldType::Text, "x".into()).is_err());
+
+        let field = CustomField::new(" API key ", FieldType::Hidden, "sk-123".into()).unwrap();
+        assert_eq!((field.name.as_str(), field.is_hidden()), ("API key", true));
+        assert_eq!(FieldType::from_name("hidden"), Some(FieldType::Hidden));
+        assert_eq!(FieldType::from_name("secret"), None);
+    }
+
+    #[test]
+    fn test_fields_keep_order_and_default_to_empty() {
+        let mut entry = VaultEntry::new("Bank", EntryKind::Note(NoteData { text: String::new() }));
+        entry.fields = vec![
+            CustomField::new("Account", FieldType::Text, "0042".into()).unwrap(),
+            CustomField::new("Memorable word", FieldType::Hidden, "otter".into()).unwrap(),
+        ];
+        let bytes = serde_cbor::to_vec(&entry).unwrap();
+        let decoded: VaultEntry = serde_cbor::from_slice(&bytes).unwrap();
+        let names: Vec<&str> = decoded.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["Account", "Memorable word"]);
+        assert_eq!(decoded.fields[1].field_type, FieldType::Hidden);
+
+        // Bodies written before custom fields existed
+        let mut older = serde_cbor::value::to_value(&entry).unwrap();
+        if let serde_cbor::Value::Map(map) = &mut older {
+            map.remove(&serde_cbor::Value::Text("fields".into()));
+        }
+        let decoded: VaultEntry = serde_cbor::value::from_value(older).unwrap();
+        assert!(decoded.fields.is_empty());
+    }
+}
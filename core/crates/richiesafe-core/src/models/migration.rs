@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use crate::error::{Result, RichieSafeError};
+use crate::models::entry::{
+    CardData, EntryKind, FileData, LoginData, NoteData, VaultEntry, VaultState, SCHEMA_VERSION,
+};
+
+/// Type names the web UI used to store in `tags[0]` under schema v1.
+const LEGACY_TYPE_TAGS: [&str; 4] = ["password", "card", "note", "image"];
+
+#[derive(Deserialize)]
+struct SchemaProbe {
+    schema_version: u16,
+}
+
+/// Entry layout of `schema_version: 1`: the entry type lived in `tags[0]` and every
+/// kind was squeezed into username/password/url/notes.
+#[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct LegacyEntryV1 {
+    #[zeroize(skip)]
+    pub id: Uuid,
+    pub title: String,
+    pub username: String,
+    pub password: Option<String>,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    #[zeroize(skip)]
+    pub created_at: DateTime<Utc>,
+    #[zeroize(skip)]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct LegacyStateV1 {
+    #[zeroize(skip)]
+    pub schema_version: u16,
+    #[zeroize(skip)]
+    pub vault_uuid: Uuid,
+    pub entries: Vec<LegacyEntryV1>,
+    #[zeroize(skip)]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Decodes a CBOR vault body of any supported schema into the current `VaultState`.
+pub fn decode_state(bytes: &[u8]) -> Result<VaultState> {
+    let probe: SchemaProbe = serde_cbor::from_slice(bytes)
+        .map_err(|e| RichieSafeError::CborDecode(e.to_string()))?;
+
+    match probe.schema_version {
+        1 => {
+            let mut legacy: LegacyStateV1 = serde_cbor::from_slice(bytes)
+                .map_err(|e| RichieSafeError::CborDecode(e.to_string()))?;
+            Ok(migrate_v1(&mut legacy))
+        }
+        SCHEMA_VERSION => serde_cbor::from_slice(bytes)
+            .map_err(|e| RichieSafeError::CborDecode(e.to_string())),
+        v => Err(RichieSafeError::UnsupportedSchema(v)),
+    }
+}
+
+fn migrate_v1(legacy: &mut LegacyStateV1) -> VaultState {
+    VaultState {
+        schema_version: SCHEMA_VERSION,
+        vault_uuid: legacy.vault_uuid,
+        entries: legacy.entries.iter_mut().map(migrate_entry).collect(),
+        created_at: legacy.created_at,
+    }
+}
+
+fn migrate_entry(e: &mut LegacyEntryV1) -> VaultEntry {
+    let mut tags = std::mem::take(&mut e.tags);
+    let type_tag = match tags.first() {
+        Some(t) if LEGACY_TYPE_TAGS.contains(&t.as_str()) => tags.remove(0),
+        _ => "password".to_string(),
+    };
+
+    let (kind, notes) = kind_from_legacy(
+        &type_tag,
+        std::mem::take(&mut e.username),
+        e.password.take(),
+        e.url.take(),
+        e.notes.take(),
+    );
+
+    VaultEntry {
+        id: e.id,
+        title: std::mem::take(&mut e.title),
+        kind,
+        notes,
+        tags,
+        fields: Vec::new(),
+        attachments: Vec::new(),
+        otp: None,
+        created_at: e.created_at,
+        updated_at: e.updated_at,
+    }
+}
+
+/// Maps the (type, username, password, url, notes) slots used by the v1 schema and the
+/// web UI onto a typed `EntryKind`. Returns the kind and the leftover free-form notes.
+pub fn kind_from_legacy(
+    type_tag: &str,
+    username: String,
+    password: Option<String>,
+    url: Option<String>,
+    notes: Option<String>,
+) -> (EntryKind, Option<String>) {
+    match type_tag {
+        // UI: number in username, CVV in password, expiry in notes
+        "card" => (
+            EntryKind::Card(CardData {
+                holder: None,
+                number: username,
+                expiry: notes,
+                cvv: password,
+            }),
+            None,
+        ),
+        // UI: note body in password
+        "note" => match password {
+            Some(text) => (EntryKind::Note(NoteData { text }), notes),
+            None => (EntryKind::Note(NoteData { text: notes.unwrap_or_default() }), None),
+        },
+        // UI: file name in username, data URL in password
+        "image" => {
+            let (mime_type, data) = decode_data_url(password.as_deref().unwrap_or_default());
+            (
+                EntryKind::File(FileData {
+                    file_name: username,
+                    mime_type,
+                    data,
+                }),
+                notes,
+            )
+        }
+        _ => (EntryKind::Login(LoginData { username, password, url }), notes),
+    }
+}
+
+/// Splits `data:<mime>;base64,<payload>`. Anything that is not a base64 data URL is
+/// kept verbatim so no user content is lost.
+pub fn decode_data_url(value: &str) -> (Option<String>, Vec<u8>) {
+    if let Some(rest) = value.strip_prefix("data:") {
+        if let Some((meta, payload)) = rest.split_once(',') {
+            if let Some(mime) = meta.strip_suffix(";base64") {
+                if let Ok(data) = BASE64.decode(payload) {
+                    let mime = (!mime.is_empty()).then(|| mime.to_string());
+                    return (mime, data);
+                }
+            }
+        }
+    }
+    (None, value.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy(type_tag: &str, username: &str, password: Option<&str>, notes: Option<&str>) -> LegacyEntryV1 {
+        LegacyEntryV1 {
+            id: Uuid::new_v4(),
+            title: format!("{type_tag} entry"),
+            username: username.to_string(),
+            password: password.map(str::to_string),
+            url: None,
+            notes: notes.map(str::to_string),
+            tags: vec![type_tag.to_string(), "work".to_string()],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_v1_state_migrates_tag_encoded_entries() {
+        let png = BASE64.encode([0x89, b'P', b'N', b'G']);
+        let state = LegacyStateV1 {
+            schema_version: 1,
+            vault_uuid: Uuid::new_v4(),
+            entries: vec![
+                legacy("password", "alice", Some("pw"), None),
+                legacy("card", "4111111111111111", Some("123"), Some("12/29")),
+                legacy("note", "", Some("secret text"), None),
+                legacy("image", "scan.png", Some(&format!("data:image/png;base64,{png}")), None),
+            ],
+            created_at: Utc::now(),
+        };
+        let bytes = serde_cbor::to_vec(&state).unwrap();
+
+        let migrated = decode_state(&bytes).unwrap();
+        assert_eq!(migrated.schema_version, SCHEMA_VERSION);
+        assert_eq!(migrated.vault_uuid, state.vault_uuid);
+        assert_eq!(migrated.entries.len(), 4);
+
+        for (old, new) in state.entries.iter().zip(&migrated.entries) {
+            assert_eq!(old.id, new.id);
+            assert_eq!(old.created_at, new.created_at);
+            assert_eq!(new.tags, vec!["work".to_string()]);
+        }
+
+        match &migrated.entries[0].kind {
+            EntryKind::Login(l) => {
+                assert_eq!(l.username, "alice");
+                assert_eq!(l.password.as_deref(), Some("pw"));
+            }
+            other => panic!("expected login, got {}", other.name()),
+        }
+        match &migrated.entries[1].kind {
+            EntryKind::Card(c) => {
+                assert_eq!(c.number, "4111111111111111");
+                assert_eq!(c.cvv.as_deref(), Some("123"));
+                assert_eq!(c.expiry.as_deref(), Some("12/29"));
+            }
+            other => panic!("expected card, got {}", other.name()),
+        }
+        assert!(migrated.entries[1].notes.is_none());
+        match &migrated.entries[2].kind {
+            EntryKind::Note(n) => assert_eq!(n.text, "secret text"),
+            other => panic!("expected note, got {}", other.name()),
+        }
+        match &migrated.entries[3].kind {
+            EntryKind::File(f) => {
+                assert_eq!(f.file_name, "scan.png");
+                assert_eq!(f.mime_type.as_deref(), Some("image/png"));
+                assert_eq!(f.data, vec![0x89, b'P', b'N', b'G']);
+            }
+            other => panic!("expected file, got {}", other.name()),
+        }
+    }
+
+    #[test]
+    fn test_untagged_v1_entry_becomes_login() {
+        let mut entry = legacy("password", "bob", None, Some("n"));
+        entry.tags = vec!["personal".to_string()];
+        let migrated = migrate_entry(&mut entry);
+        assert_eq!(migrated.kind.name(), "login");
+        assert_eq!(migrated.tags, vec!["personal".to_string()]);
+        assert_eq!(migrated.notes.as_deref(), Some("n"));
+    }
+
+    #[test]
+    fn test_current_schema_roundtrips_and_unknown_is_rejected() {
+        let mut state = VaultState::new();
+        state.entries.push(VaultEntry::new("Mail", EntryKind::Login(LoginData::default())));
+        let bytes = serde_cbor::to_vec(&state).unwrap();
+        assert_eq!(decode_state(&bytes).unwrap().entries[0].title, "Mail");
+
+        state.schema_version = 99;
+        let bytes = serde_cbor::to_vec(&state).unwrap();
+        assert_eq!(decode_state(&bytes).unwrap_err(), RichieSafeError::UnsupportedSchema(99));
+    }
+}
@@ -1 +1,2 @@
 pub mod entry;
+pub mod migration;
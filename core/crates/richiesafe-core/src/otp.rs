@@ -0,0 +1,339 @@
+//! One-time password seeds (HOTP, RFC 4226; TOTP, RFC 6238) stored on entries.
+//!
+//! Seeds are imported from `otpauth://` URIs as shown in enrolment QR codes and
+//! live in the vault body like any other secret field.
+
+use crate::error::{Result, RichieSafeError};
+use hmac::{Hmac, Mac};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum OtpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl OtpAlgorithm {
+    fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA1" => Ok(Self::Sha1),
+            "SHA256" => Ok(Self::Sha256),
+            "SHA512" => Ok(Self::Sha512),
+            _ => Err(invalid("unsupported algorithm")),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+            Self::Sha512 => "SHA512",
+        }
+    }
+
+    fn mac(self, key: &[u8], message: &[u8]) -> Zeroizing<Vec<u8>> {
+        fn run<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Zeroizing<Vec<u8>> {
+            let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC takes any key length");
+            mac.update(message);
+            Zeroizing::new(mac.finalize().into_bytes().to_vec())
+        }
+        match self {
+            Self::Sha1 => run::<Hmac<Sha1>>(key, message),
+            Self::Sha256 => run::<Hmac<Sha256>>(key, message),
+            Self::Sha512 => run::<Hmac<Sha512>>(key, message),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OtpKind {
+    /// Time-based; a new code every `period` seconds
+    Totp { period: u64 },
+    /// Counter-based; `counter` is the next value to use
+    Hotp { counter: u64 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct OtpSecret {
+    #[serde(with = "serde_bytes")]
+    pub secret: Vec<u8>,
+    #[zeroize(skip)]
+    pub kind: OtpKind,
+    #[zeroize(skip)]
+    pub algorithm: OtpAlgorithm,
+    #[zeroize(skip)]
+    pub digits: u8,
+    pub issuer: Option<String>,
+    pub account: Option<String>,
+}
+
+/// A code and, for TOTP, how long it stays valid.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct OtpCode {
+    pub code: String,
+    #[zeroize(skip)]
+    pub expires_in: Option<u64>,
+}
+
+fn invalid(reason: &str) -> RichieSafeError {
+    RichieSafeError::InvalidOtp(reason.to_string())
+}
+
+impl OtpSecret {
+    /// A TOTP seed with the usual authenticator defaults (SHA1, 6 digits, 30 s).
+    pub fn totp(secret: Vec<u8>) -> Self {
+        OtpSecret {
+            secret,
+            kind: OtpKind::Totp { period: 30 },
+            algorithm: OtpAlgorithm::Sha1,
+            digits: 6,
+            issuer: None,
+            account: None,
+        }
+    }
+
+    /// Parses `otpauth://totp/Issuer:account?secret=BASE32&issuer=…&algorithm=…&digits=…&period=…`
+    /// (or `otpauth://hotp/…&counter=…`). The `issuer` parameter wins over a label prefix.
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let rest = uri.strip_prefix("otpauth://").ok_or_else(|| invalid("not an otpauth:// URI"))?;
+        let (kind, rest) = rest.split_once('/').ok_or_else(|| invalid("missing label"))?;
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let decode = |s: &str| -> Result<String> {
+            let s = s.replace('+', " ");
+            percent_decode_str(&s).decode_utf8()
+                .map(|s| s.trim().to_string())
+                .map_err(|_| invalid("label or parameter is not UTF-8"))
+        };
+        let label = decode(label)?;
+        let (label_issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.trim().to_string()), account.trim().to_string()),
+            None => (None, label),
+        };
+
+        let mut secret = None;
+        let mut issuer = None;
+        let mut algorithm = OtpAlgorithm::Sha1;
+        let mut digits = 6;
+        let mut period = 30;
+        let mut counter = None;
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = Zeroizing::new(decode(value)?);
+            let number = || value.parse::<u64>().map_err(|_| invalid("numeric parameter expected"));
+            match key.to_ascii_lowercase().as_str() {
+                "secret" => secret = Some(base32_decode(&value)?),
+                "issuer" => issuer = Some(value.to_string()),
+                "algorithm" => algorithm = OtpAlgorithm::from_name(&value)?,
+                "digits" => digits = u8::try_from(number()?).map_err(|_| invalid("digits out of range"))?,
+                "period" => period = number()?,
+                "counter" => counter = Some(number()?),
+                _ => {}
+            }
+        }
+
+        let kind = match kind.to_ascii_lowercase().as_str() {
+            "totp" => OtpKind::Totp { period },
+            "hotp" => OtpKind::Hotp { counter: counter.ok_or_else(|| invalid("hotp URI without counter"))? },
+            _ => return Err(invalid("type must be totp or hotp")),
+        };
+        let otp = OtpSecret {
+            secret: secret.ok_or_else(|| invalid("missing secret"))?,
+            kind,
+            algorithm,
+            digits,
+            issuer: issuer.or(label_issuer).filter(|i| !i.is_empty()),
+            account: Some(account).filter(|a| !a.is_empty()),
+        };
+        otp.validate()?;
+        Ok(otp)
+    }
+
+    /// `otpauth://` URI for re-enrolling the seed in another authenticator.
+    pub fn to_uri(&self) -> Zeroizing<String> {
+        let encode = |s: &str| utf8_percent_encode(s, NON_ALPHANUMERIC).to_string();
+        let label = match (&self.issuer, &self.account) {
+            (Some(issuer), Some(account)) => format!("{}:{}", encode(issuer), encode(account)),
+            (Some(name), None) | (None, Some(name)) => encode(name),
+            (None, None) => String::new(),
+        };
+        let (kind, moving) = match self.kind {
+            OtpKind::Totp { period } => ("totp", format!("period={period}")),
+            OtpKind::Hotp { counter } => ("hotp", format!("counter={counter}")),
+        };
+        let issuer = self.issuer.as_deref().map(|i| format!("&issuer={}", encode(i))).unwrap_or_default();
+        Zeroizing::new(format!(
+            "otpauth://{kind}/{label}?secret={}&algorithm={}&digits={}&{moving}{issuer}",
+            *base32_encode(&self.secret),
+            self.algorithm.name(),
+            self.digits,
+        ))
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.secret.is_empty() {
+            return Err(invalid("empty secret"));
+        }
+        if !(6..=8).contains(&self.digits) {
+            return Err(invalid("digits must be 6 to 8"));
+        }
+        if self.kind == (OtpKind::Totp { period: 0 }) {
+            return Err(invalid("period must be positive"));
+        }
+        Ok(())
+    }
+
+    /// RFC 4226 code for `counter`.
+    pub fn hotp(&self, counter: u64) -> Result<Zeroizing<String>> {
+        self.validate()?;
+        let mac = self.algorithm.mac(&self.secret, &counter.to_be_bytes());
+        let offset = (mac[mac.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes(mac[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+        let code = u64::from(binary) % 10u64.pow(u32::from(self.digits));
+        Ok(Zeroizing::new(format!("{code:0width$}", width = self.digits as usize)))
+    }
+
+    /// The code to show at `unix_secs`: the RFC 6238 code for TOTP, the code for
+    /// the stored counter for HOTP.
+    pub fn code_at(&self, unix_secs: u64) -> Result<OtpCode> {
+        match self.kind {
+            OtpKind::Totp { period } => {
+                self.validate()?;
+                Ok(OtpCode {
+                    code: self.hotp(unix_secs / period)?.to_string(),
+                    expires_in: Some(period - unix_secs % period),
+                })
+            }
+            OtpKind::Hotp { counter } => Ok(OtpCode { code: self.hotp(counter)?.to_string(), expires_in: None }),
+        }
+    }
+}
+
+/// RFC 4648 base32, case-insensitive, ignoring spaces, dashes and padding.
+pub fn base32_decode(text: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !matches!(c, b' ' | b'-' | b'=')) {
+        let value = BASE32_ALPHABET.iter()
+            .position(|&a| a == c.to_ascii_uppercase())
+            .ok_or_else(|| invalid("secret is not base32"))?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    buffer.zeroize();
+    Ok(out)
+}
+
+/// Unpadded RFC 4648 base32, as authenticator apps expect it.
+pub fn base32_encode(bytes: &[u8]) -> Zeroizing<String> {
+    let mut out = Zeroizing::new(String::with_capacity(bytes.len().div_ceil(5) * 8));
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &b in bytes {
+        buffer = (buffer << 8) | u32::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    buffer.zeroize();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rfc_seed(algorithm: OtpAlgorithm) -> OtpSecret {
+        let seed = match algorithm {
+            OtpAlgorithm::Sha1 => "12345678901234567890".to_string(),
+            OtpAlgorithm::Sha256 => "12345678901234567890123456789012".to_string(),
+            OtpAlgorithm::Sha512 => "1234567890".repeat(6) + "1234",
+        };
+        let mut otp = OtpSecret::totp(seed.into_bytes());
+        otp.algorithm = algorithm;
+        otp.digits = 8;
+        otp
+    }
+
+    #[test]
+    fn test_rfc4226_vectors() {
+        let mut otp = rfc_seed(OtpAlgorithm::Sha1);
+        otp.digits = 6;
+        let expected = ["755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583", "399871", "520489"];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(*otp.hotp(counter as u64).unwrap(), *code);
+        }
+    }
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        let vectors: [(u64, [&str; 3]); 6] = [
+            (59, ["94287082", "46119246", "90693936"]),
+            (1111111109, ["07081804", "68084774", "25091201"]),
+            (1111111111, ["14050471", "67062674", "99943326"]),
+            (1234567890, ["89005924", "91819424", "93441116"]),
+            (2000000000, ["69279037", "90698825", "38618901"]),
+            (20000000000, ["65353130", "77737706", "47863826"]),
+        ];
+        let algorithms = [OtpAlgorithm::Sha1, OtpAlgorithm::Sha256, OtpAlgorithm::Sha512];
+        for (time, codes) in vectors {
+            for (algorithm, code) in algorithms.into_iter().zip(codes) {
+                assert_eq!(rfc_seed(algorithm).code_at(time).unwrap().code, code, "{algorithm:?} at {time}");
+            }
+        }
+        assert_eq!(rfc_seed(OtpAlgorithm::Sha1).code_at(59).unwrap().expires_in, Some(1));
+    }
+
+    #[test]
+    fn test_uri_roundtrip() {
+        let otp = OtpSecret::from_uri(
+            "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME+Co&algorithm=SHA256&digits=8&period=60",
+        ).unwrap();
+        assert_eq!(otp.issuer.as_deref(), Some("ACME Co"));
+        assert_eq!(otp.account.as_deref(), Some("john.doe@email.com"));
+        assert_eq!((otp.kind, otp.algorithm, otp.digits), (OtpKind::Totp { period: 60 }, OtpAlgorithm::Sha256, 8));
+        assert_eq!(*base32_encode(&otp.secret), "HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ");
+
+        let again = OtpSecret::from_uri(&otp.to_uri()).unwrap();
+        assert_eq!((again.secret.clone(), again.issuer.clone(), again.account.clone()), (otp.secret.clone(), otp.issuer.clone(), otp.account.clone()));
+        assert_eq!(again.code_at(1_700_000_000).unwrap(), otp.code_at(1_700_000_000).unwrap());
+
+        let hotp = OtpSecret::from_uri("otpauth://hotp/Example?secret=gezdgnbvgy3tqojqgezdgnbvgy3tqojq&counter=1").unwrap();
+        assert_eq!(hotp.code_at(0).unwrap(), OtpCode { code: "287082".into(), expires_in: None });
+    }
+
+    #[test]
+    fn test_invalid_uris() {
+        for uri in [
+            "https://example.com",
+            "otpauth://totp/x",
+            "otpauth://totp/x?secret=not*base32",
+            "otpauth://totp/x?secret=JBSWY3DP&digits=12",
+            "otpauth://totp/x?secret=JBSWY3DP&period=0",
+            "otpauth://totp/x?secret=JBSWY3DP&algorithm=MD5",
+            "otpauth://hotp/x?secret=JBSWY3DP",
+            "otpauth://steam/x?secret=JBSWY3DP",
+        ] {
+            assert!(matches!(OtpSecret::from_uri(uri), Err(RichieSafeError::InvalidOtp(_))), "{uri}");
+        }
+    }
+}
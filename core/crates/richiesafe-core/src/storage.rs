@@ -0,0 +1,548 @@
+//! File-backed vault storage (feature `std-fs`).
+//!
+//! On disk a vault file is the sealed blob followed by a 40-byte footer:
+//! SHA-256(blob) then `CHECKSUM_MAGIC`. Saves go through a temporary file,
+//! fsync and rename, so readers only ever see the old or the new file. The
+//! footer catches the cases rename cannot (copies, syncs, filesystems without
+//! atomic rename). Before each save the current file is kept as a timestamped
+//! backup next to it, and the oldest backups beyond the configured count are pruned.
+//! `open` restores the newest backup the secret unlocks when the file is missing,
+//! torn or does not parse, never when it fails authentication.
+//! Backups still open with the secrets they were written under, so saves that
+//! retire a secret go through `save_rekeyed`, which drops them all.
+//! Files written before the footer existed are accepted by `open` once their body
+//! authenticates, and rewritten with a footer.
+//! The failed-attempt record (see `vault::attempts`) lives beside it as `<vault>.attempts`
+//! and every `open` goes through it. Anyone who can write to the directory can
+//! delete that file or put back an older copy of it, and either one resets the count.
+
+use crate::error::{Result, RichieSafeError};
+use crate::vault::attempts::{self, AttemptRecord, ThrottlePolicy};
+use crate::vault::format;
+use crate::vault::ops::VaultHandle;
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const CHECKSUM_MAGIC: &[u8; 8] = b"RSAFESUM";
+/// SHA-256(32) + magic(8)
+pub const FOOTER_SIZE: usize = 40;
+const BACKUP_SUFFIX: &str = ".bak";
+const ATTEMPTS_SUFFIX: &str = ".attempts";
+
+/// A vault unlocked through `VaultStore::open`.
+pub struct Opened {
+    pub handle: VaultHandle,
+    /// Set when the main file was damaged and this backup was restored over it.
+    pub restored_from: Option<PathBuf>,
+}
+
+pub struct VaultStore {
+    path: PathBuf,
+    keep_backups: usize,
+}
+
+impl VaultStore {
+    pub fn new(path: impl Into<PathBuf>, keep_backups: usize) -> Self {
+        Self { path: path.into(), keep_backups }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Atomically replaces the vault file, keeping the previous valid one as a backup.
+    pub fn save(&self, blob: &[u8]) -> Result<()> {
+        if self.keep_backups > 0 && read_verified(&self.path).is_ok() {
+            let backup = self.backup_path_now();
+            fs::copy(&self.path, &backup)?;
+        }
+
+        write_atomic(&self.path, &framed(blob))?;
+
+        self.prune()
+    }
+
+    /// Saves a blob whose unlock methods changed (PIN change, recovery rotation,
+    /// rekey) and deletes every backup, since those still open with the retired secret.
+    /// The current file is not kept as a backup either.
+    pub fn save_rekeyed(&self, blob: &[u8]) -> Result<()> {
+        write_atomic(&self.path, &framed(blob))?;
+        for old in self.backups()? {
+            fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the vault file and checks its footer and header framing.
+    pub fn load(&self) -> Result<Vec<u8>> {
+        read_verified(&self.path)
+    }
+
+    /// Backups, newest first.
+    pub fn backups(&self) -> Result<Vec<PathBuf>> {
+        let dir = self.dir();
+        let prefix = self.backup_prefix();
+        let mut found: Vec<PathBuf> = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(&prefix) && n.ends_with(BACKUP_SUFFIX))
+                })
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        // Timestamps are fixed-width, so name order is age order
+        found.sort();
+        found.reverse();
+        Ok(found)
+    }
+
+    /// Loads and unlocks the vault through the attempt record (see `vault::attempts`).
+    /// If the file is missing, torn or unparseable, the newest backup that `secret` unlocks is
+    /// written back as the vault file and opened instead. Anything that fails
+    /// authentication or policy (wrong secret, tampered header, undecryptable body)
+    /// is returned as is and never triggers a restore. However many candidates are
+    /// tried, a wrong secret counts as one failure.
+    pub fn open(&self, secret: &str, device_key: &[u8], policy: &ThrottlePolicy) -> Result<Opened> {
+        let (candidates, damage) = match self.load() {
+            Ok(blob) => (vec![(blob, Source::Current)], None),
+            Err(e) if !is_damage(&e) => return Err(e),
+            Err(e) => {
+                // A footerless file from before checksums: only a body that authenticates
+                // proves it is complete, so it is rewritten with a footer on first open
+                let mut found: Vec<(Vec<u8>, Source)> = read_legacy(&self.path)
+                    .map(|blob| (blob, Source::Legacy))
+                    .into_iter()
+                    .collect();
+                for backup in self.backups()? {
+                    if let Ok(blob) = read_verified(&backup) {
+                        found.push((blob, Source::Backup(backup)));
+                    }
+                }
+                (found, Some(e))
+            }
+        };
+        let Some((first, _)) = candidates.first() else {
+            return Err(damage.unwrap_or(RichieSafeError::TornWrite));
+        };
+        let start = self.load_attempts(first, device_key, policy)?;
+
+        let mut after = start.clone();
+        let mut failure: Option<RichieSafeError> = None;
+        for (blob, source) in candidates {
+            let mut record = start.clone();
+            match attempts::unlock(&blob, secret, &mut record, policy) {
+                Ok(unlocked) => {
+                    self.save_attempts(&record, device_key)?;
+                    match (unlocked.resealed, &source) {
+                        (Some(resealed), _) => self.save_rekeyed(&resealed)?,
+                        (None, Source::Current) => {}
+                        (None, _) => self.save(&blob)?,
+                    }
+                    let restored_from = match source {
+                        Source::Backup(path) => Some(path),
+                        _ => None,
+                    };
+                    return Ok(Opened { handle: unlocked.handle, restored_from });
+                }
+                Err(e) => {
+                    if record.failures() > after.failures() {
+                        after = record;
+                    }
+                    if failure.as_ref().is_none_or(|f| !is_refusal(f) && is_refusal(&e)) {
+                        failure = Some(e);
+                    }
+                }
+            }
+        }
+        self.save_attempts(&after, device_key)?;
+
+        let failure = failure.expect("at least one candidate was tried");
+        Err(match damage {
+            Some(damage) if !is_refusal(&failure) => damage,
+            _ => failure,
+        })
+    }
+
+    pub fn attempts_path(&self) -> PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(ATTEMPTS_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// Reads the attempt record for the vault in `vault_blob`. A missing record starts
+    /// fresh; an invalid one fails closed as if `policy` had already tripped.
+    pub fn load_attempts(&self, vault_blob: &[u8], device_key: &[u8], policy: &ThrottlePolicy) -> Result<AttemptRecord> {
+        match fs::read(self.attempts_path()) {
+            Ok(bytes) => match AttemptRecord::from_bytes(&bytes, device_key, vault_blob) {
+                Err(RichieSafeError::AttemptRecordTampered) => AttemptRecord::tripped(vault_blob, policy),
+                other => other,
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => AttemptRecord::new(vault_blob),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save_attempts(&self, record: &AttemptRecord, device_key: &[u8]) -> Result<()> {
+        Ok(write_atomic(&self.attempts_path(), &record.to_bytes(device_key))?)
+    }
+
+    fn dir(&self) -> PathBuf {
+        match self.path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    fn backup_prefix(&self) -> String {
+        let name = self.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        format!("{name}.")
+    }
+
+    fn backup_path_now(&self) -> PathBuf {
+        let stamp = Utc::now().format("%Y%m%dT%H%M%S%.9fZ");
+        self.dir().join(format!("{}{stamp}{BACKUP_SUFFIX}", self.backup_prefix()))
+    }
+
+    fn prune(&self) -> Result<()> {
+        for old in self.backups()?.into_iter().skip(self.keep_backups) {
+            fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a candidate blob in `VaultStore::open` came from.
+enum Source {
+    Current,
+    Legacy,
+    Backup(PathBuf),
+}
+
+/// Errors about the secret or the throttle rather than the bytes.
+fn is_refusal(e: &RichieSafeError) -> bool {
+    matches!(
+        e,
+        RichieSafeError::WrongSecret
+            | RichieSafeError::RecoveryRequired
+            | RichieSafeError::Throttled(_)
+            | RichieSafeError::LockedOut
+    )
+}
+
+/// Errors that mean the file on disk is missing, incomplete or does not frame as a
+/// vault blob. Authentication and policy failures are deliberately absent: restoring
+/// on those would let anyone who can flip a byte roll the vault back. So are
+/// unsupported versions, which a newer app may have written.
+fn is_damage(e: &RichieSafeError) -> bool {
+    matches!(
+        e,
+        RichieSafeError::Io(_)
+            | RichieSafeError::TornWrite
+            | RichieSafeError::TruncatedBlob
+            | RichieSafeError::CorruptedHeader(_)
+    )
+}
+
+fn framed(blob: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(blob.len() + FOOTER_SIZE);
+    framed.extend_from_slice(blob);
+    framed.extend_from_slice(&Sha256::digest(blob));
+    framed.extend_from_slice(CHECKSUM_MAGIC);
+    framed
+}
+
+/// The whole file, if it has no footer but frames as a vault blob.
+fn read_legacy(path: &Path) -> Option<Vec<u8>> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.ends_with(CHECKSUM_MAGIC) || format::split(&bytes).is_err() {
+        return None;
+    }
+    Some(bytes)
+}
+
+fn read_verified(path: &Path) -> Result<Vec<u8>> {
+    let mut bytes = fs::read(path)?;
+
+    // Every file written by `save` ends with the footer; a missing or wrong one
+    // means the write did not complete.
+    if bytes.len() < FOOTER_SIZE || !bytes.ends_with(CHECKSUM_MAGIC) {
+        return Err(RichieSafeError::TornWrite);
+    }
+    let body_len = bytes.len() - FOOTER_SIZE;
+    if Sha256::digest(&bytes[..body_len]).as_slice() != &bytes[body_len..body_len + 32] {
+        return Err(RichieSafeError::TornWrite);
+    }
+    bytes.truncate(body_len);
+
+    format::split(&bytes)?;
+    Ok(bytes)
+}
+
+/// Writes and syncs `bytes` to a file only the owner can read (mode 0600 on Unix).
+/// Also used by hosts for the device key that signs the attempt record.
+pub fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    write_private(&tmp, bytes)?;
+    fs::rename(&tmp, path)?;
+
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::kdf::KdfParams;
+    use crate::models::entry::{EntryKind, NoteData, VaultEntry};
+    use crate::vault::header::VaultType;
+    use crate::vault::ops;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("richiesafe-storage-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const DEVICE_KEY: &[u8] = b"device";
+
+    fn open(store: &VaultStore, secret: &str) -> Result<Opened> {
+        store.open(secret, DEVICE_KEY, &ThrottlePolicy::default())
+    }
+
+    fn new_blob() -> Vec<u8> {
+        let params = KdfParams::FLOOR;
+        ops::create_vault(VaultType::Real, "1234", "recovery", params, params).unwrap()
+    }
+
+    fn with_note(blob: &[u8], text: &str) -> Vec<u8> {
+        let mut handle = ops::unlock_vault(blob, "1234").unwrap();
+        handle.add_entry(VaultEntry::new(text, EntryKind::Note(NoteData { text: text.into() })));
+        handle.seal().unwrap()
+    }
+
+    #[test]
+    fn test_save_rotates_backups() {
+        let dir = TempDir::new();
+        let store = VaultStore::new(dir.0.join("vault.rsafe"), 2);
+
+        let mut blob = new_blob();
+        for i in 0..4 {
+            blob = with_note(&blob, &format!("note {i}"));
+            store.save(&blob).unwrap();
+        }
+
+        assert_eq!(store.load().unwrap(), blob);
+        assert_eq!(store.backups().unwrap().len(), 2);
+        assert!(!dir.0.join("vault.rsafe.tmp").exists());
+
+        // Newest backup is the save before last
+        let newest = read_verified(&store.backups().unwrap()[0]).unwrap();
+        assert_eq!(ops::unlock_vault(&newest, "1234").unwrap().state.entries.len(), 3);
+    }
+
+    #[test]
+    fn test_torn_write_is_detected_and_restored() {
+        let dir = TempDir::new();
+        let store = VaultStore::new(dir.0.join("vault.rsafe"), 3);
+        let first = with_note(&new_blob(), "first");
+        let second = with_note(&first, "second");
+        store.save(&first).unwrap();
+        store.save(&second).unwrap();
+
+        // Chop the file mid-ciphertext
+        let full = fs::read(store.path()).unwrap();
+        fs::write(store.path(), &full[..full.len() - 60]).unwrap();
+        assert_eq!(store.load(), Err(RichieSafeError::TornWrite));
+
+        // The header still rejects a wrong secret, and no backup opens with it either
+        assert_eq!(open(&store, "0000").err(), Some(RichieSafeError::WrongSecret));
+        assert_eq!(store.load(), Err(RichieSafeError::TornWrite));
+
+        let opened = open(&store, "1234").unwrap();
+        assert!(opened.restored_from.is_some());
+        assert_eq!(opened.handle.state.entries.len(), 1);
+        assert_eq!(store.load().unwrap(), first);
+    }
+
+    #[test]
+    fn test_unparseable_file_is_restored() {
+        let dir = TempDir::new();
+        let store = VaultStore::new(dir.0.join("vault.rsafe"), 3);
+        let first = with_note(&new_blob(), "first");
+        store.save(&first).unwrap();
+        store.save(&first).unwrap();
+
+        // Footer matches, but the blob inside does not frame as a vault
+        write_atomic(store.path(), &framed(&first[..30])).unwrap();
+        assert_eq!(store.load(), Err(RichieSafeError::TruncatedBlob));
+
+        let opened = open(&store, "1234").unwrap();
+        assert!(opened.restored_from.is_some());
+        assert_eq!(store.load().unwrap(), first);
+    }
+
+    #[test]
+    fn test_authentication_failures_never_restore() {
+        let dir = TempDir::new();
+        let store = VaultStore::new(dir.0.join("vault.rsafe"), 3);
+        let first = new_blob();
+        store.save(&first).unwrap();
+
+        // Well-framed file whose body was damaged before it was checksummed
+        let mut damaged = with_note(&first, "lost");
+        let last = damaged.len() - 1;
+        damaged[last] ^= 0xFF;
+        store.save(&damaged).unwrap();
+        assert_eq!(open(&store, "recovery").err(), Some(RichieSafeError::BodyDecryption));
+
+        // An edited header is tampering, not damage
+        let mut tampered = with_note(&first, "edited");
+        tampered[20] ^= 0x01; // header salt
+        store.save(&tampered).unwrap();
+        assert_eq!(open(&store, "1234").err(), Some(RichieSafeError::HeaderTampered));
+        assert_eq!(fs::read(store.path()).unwrap(), framed(&tampered));
+    }
+
+    #[test]
+    fn test_rotated_secret_opens_nothing() {
+        let dir = TempDir::new();
+        let store = VaultStore::new(dir.0.join("vault.rsafe"), 3);
+        let mut blob = new_blob();
+        for i in 0..3 {
+            blob = with_note(&blob, &format!("note {i}"));
+            store.save(&blob).unwrap();
+        }
+        assert!(!store.backups().unwrap().is_empty());
+
+        let mut handle = ops::unlock_vault(&blob, "recovery").unwrap();
+        let rotated = ops::rotate_recovery(&mut handle, "new phrase", KdfParams::FLOOR).unwrap();
+        store.save_rekeyed(&rotated).unwrap();
+        assert!(store.backups().unwrap().is_empty());
+
+        // Not even by tearing the live file to force a restore
+        let full = fs::read(store.path()).unwrap();
+        fs::write(store.path(), &full[..full.len() - 60]).unwrap();
+        assert!(open(&store, "recovery").is_err());
+        for file in fs::read_dir(&dir.0).unwrap() {
+            let bytes = fs::read(file.unwrap().path()).unwrap();
+            assert!(ops::unlock_vault(&bytes[..bytes.len().saturating_sub(FOOTER_SIZE)], "recovery").is_err());
+        }
+        assert!(open(&store, "new phrase").is_err());
+    }
+
+    #[test]
+    fn test_attempts_persist_and_fail_closed() {
+        let dir = TempDir::new();
+        let store = VaultStore::new(dir.0.join("vault.rsafe"), 1);
+        store.save(&new_blob()).unwrap();
+        let blob = store.load().unwrap();
+        let policy = ThrottlePolicy::default();
+
+        assert_eq!(store.load_attempts(&blob, DEVICE_KEY, &policy).unwrap().failures(), 0);
+        assert_eq!(open(&store, "0000").err(), Some(RichieSafeError::WrongSecret));
+        assert_eq!(store.load_attempts(&blob, DEVICE_KEY, &policy).unwrap().failures(), 1);
+        assert!(store.backups().unwrap().is_empty());
+
+        fs::write(store.attempts_path(), b"garbage").unwrap();
+        assert_eq!(store.load_attempts(&blob, DEVICE_KEY, &policy).unwrap().failures(), policy.max_failures.unwrap());
+        assert!(matches!(open(&store, "1234").err(), Some(RichieSafeError::Throttled(_))));
+    }
+
+    #[test]
+    fn test_open_is_throttled() {
+        let dir = TempDir::new();
+        let store = VaultStore::new(dir.0.join("vault.rsafe"), 3);
+        let first = new_blob();
+        store.save(&first).unwrap();
+        store.save(&with_note(&first, "second")).unwrap();
+        let policy = ThrottlePolicy::default();
+
+        for _ in 0..policy.free_attempts {
+            assert_eq!(open(&store, "0000").err(), Some(RichieSafeError::WrongSecret));
+        }
+        // A torn file has the current file and a backup to try; the guess still counts once
+        let full = fs::read(store.path()).unwrap();
+        fs::write(store.path(), &full[..full.len() - 60]).unwrap();
+        assert_eq!(open(&store, "0000").err(), Some(RichieSafeError::WrongSecret));
+        assert_eq!(store.load_attempts(&first, DEVICE_KEY, &policy).unwrap().failures(), policy.free_attempts + 1);
+
+        // The right PIN now waits too, and nothing was restored meanwhile
+        assert!(matches!(open(&store, "1234").err(), Some(RichieSafeError::Throttled(_))));
+        assert_eq!(store.load(), Err(RichieSafeError::TornWrite));
+    }
+
+    #[test]
+    fn test_footer_is_required() {
+        let dir = TempDir::new();
+        let store = VaultStore::new(dir.0.join("vault.rsafe"), 1);
+        let blob = new_blob();
+
+        fs::write(store.path(), &blob).unwrap();
+        assert_eq!(store.load(), Err(RichieSafeError::TornWrite));
+
+        // Checksum mismatch
+        store.save(&blob).unwrap();
+        let mut bytes = fs::read(store.path()).unwrap();
+        bytes[10] ^= 1;
+        fs::write(store.path(), &bytes).unwrap();
+        assert_eq!(store.load(), Err(RichieSafeError::TornWrite));
+    }
+
+    #[test]
+    fn test_legacy_file_is_rewritten_with_footer() {
+        let dir = TempDir::new();
+        let store = VaultStore::new(dir.0.join("vault.rsafe"), 1);
+        let blob = with_note(&new_blob(), "old");
+        fs::write(store.path(), &blob).unwrap();
+
+        assert_eq!(open(&store, "0000").err(), Some(RichieSafeError::WrongSecret));
+        assert_eq!(fs::read(store.path()).unwrap(), blob);
+
+        let opened = open(&store, "1234").unwrap();
+        assert!(opened.restored_from.is_none());
+        assert_eq!(opened.handle.state.entries.len(), 1);
+        assert_eq!(store.load().unwrap(), blob);
+    }
+}
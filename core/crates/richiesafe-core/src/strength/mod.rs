@@ -0,0 +1,379 @@
+//! Strength estimation for PINs and passwords.
+//!
+//! `estimate` looks for the cheapest description of a secret as a chain of
+//! patterns (repeats, sequences, keyboard walks, dates, entries of a bundled list
+//! of common secrets) and brute-forced characters, and reports the guessing
+//! entropy of that description. `StrengthPolicy` turns the estimate into a
+//! yes/no for new PINs; the active policy is enforced by `ops::create_vault`,
+//! `ops::change_pin` and `ops::reset_pin`.
+
+use crate::error::{Result, RichieSafeError};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use zeroize::Zeroizing;
+
+/// Ranked list, most common first
+const COMMON: &str = include_str!("common.txt");
+const KEYBOARD_ROWS: [&str; 4] = ["`1234567890-=", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./"];
+/// Patterns are only searched for in this many leading characters.
+const MAX_PATTERN_LEN: usize = 256;
+const MAX_WORD_LEN: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Weakness {
+    Short,
+    Repeat,
+    Sequence,
+    KeyboardWalk,
+    Date,
+    CommonWord,
+}
+
+impl Weakness {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Short => "short",
+            Self::Repeat => "repeat",
+            Self::Sequence => "sequence",
+            Self::KeyboardWalk => "keyboard_walk",
+            Self::Date => "date",
+            Self::CommonWord => "common_word",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Strength {
+    /// log2 of the estimated number of guesses
+    pub entropy_bits: f64,
+    /// 0 (guessed almost at once) to 4 (strong)
+    pub score: u8,
+    /// Patterns the estimate relied on, without duplicates
+    pub reasons: Vec<Weakness>,
+}
+
+/// Requirements for new PINs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StrengthPolicy {
+    /// Minimum length in characters
+    pub min_length: usize,
+    /// Minimum `Strength::score`
+    pub min_score: u8,
+}
+
+impl StrengthPolicy {
+    /// Accepts anything. The process default, so hosts opt in.
+    pub const NONE: StrengthPolicy = StrengthPolicy { min_length: 0, min_score: 0 };
+    /// Six characters or more and no obvious pattern: a random 6-digit PIN passes,
+    /// `123456`, `111111` or a birthday do not.
+    pub const RECOMMENDED: StrengthPolicy = StrengthPolicy { min_length: 6, min_score: 1 };
+
+    /// Fails with `WeakSecret` naming what was wrong.
+    pub fn check(&self, secret: &str) -> Result<Strength> {
+        let strength = estimate(secret);
+        let length = crate::util::normalize_input(secret).chars().count();
+        if length < self.min_length {
+            return Err(RichieSafeError::WeakSecret(format!("shorter than {} characters", self.min_length)));
+        }
+        if strength.score < self.min_score {
+            let reasons: Vec<&str> = strength.reasons.iter().map(|r| r.name()).collect();
+            let reasons = if reasons.is_empty() { "too few possibilities".to_string() } else { reasons.join(", ") };
+            return Err(RichieSafeError::WeakSecret(reasons));
+        }
+        Ok(strength)
+    }
+}
+
+impl Default for StrengthPolicy {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+static POLICY: RwLock<StrengthPolicy> = RwLock::new(StrengthPolicy::NONE);
+
+/// Replaces the process-wide policy for new PINs.
+pub fn set_policy(policy: StrengthPolicy) {
+    *POLICY.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = policy;
+}
+
+pub fn policy() -> StrengthPolicy {
+    *POLICY.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// One way to describe `chars[start..end]` at a cost of `bits`.
+struct Match {
+    start: usize,
+    end: usize,
+    bits: f64,
+    kind: Weakness,
+}
+
+pub fn estimate(secret: &str) -> Strength {
+    let normalized = Zeroizing::new(crate::util::normalize_input(secret));
+    let chars = Zeroizing::new(normalized.chars().collect::<Vec<char>>());
+    let n = chars.len();
+    let per_char = (charset_size(&chars) as f64).log2();
+    let mut ending_at: Vec<Vec<Match>> = (0..=n).map(|_| Vec::new()).collect();
+    for m in find_matches(&chars[..n.min(MAX_PATTERN_LEN)]) {
+        ending_at[m.end].push(m);
+    }
+
+    // Cheapest cover of the first j characters: (bits, where it came from, pattern used)
+    let mut best: Vec<(f64, usize, Option<Weakness>)> = vec![(0.0, 0, None); n + 1];
+    for j in 1..=n {
+        best[j] = (best[j - 1].0 + per_char, j - 1, None);
+        for m in &ending_at[j] {
+            let bits = best[m.start].0 + m.bits;
+            if bits < best[j].0 {
+                best[j] = (bits, m.start, Some(m.kind));
+            }
+        }
+    }
+
+    let mut reasons = Vec::new();
+    let mut j = n;
+    while j > 0 {
+        let (_, from, kind) = best[j];
+        reasons.extend(kind);
+        j = from;
+    }
+    if n < 6 {
+        reasons.push(Weakness::Short);
+    }
+    reasons.sort();
+    reasons.dedup();
+
+    let entropy_bits = best[n].0;
+    Strength { entropy_bits, score: score(entropy_bits), reasons }
+}
+
+/// Thresholds at about 10^3, 10^6, 10^8 and 10^10 guesses.
+fn score(bits: f64) -> u8 {
+    match bits {
+        b if b < 10.0 => 0,
+        b if b < 20.0 => 1,
+        b if b < 27.0 => 2,
+        b if b < 33.0 => 3,
+        _ => 4,
+    }
+}
+
+fn class_size(c: char) -> u32 {
+    match c {
+        '0'..='9' => 10,
+        'a'..='z' | 'A'..='Z' => 26,
+        c if c.is_ascii() => 33,
+        _ => 100,
+    }
+}
+
+fn charset_size(chars: &[char]) -> u32 {
+    let mut size = 0;
+    if chars.iter().any(|c| c.is_ascii_digit()) { size += 10; }
+    if chars.iter().any(|c| c.is_ascii_lowercase()) { size += 26; }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) { size += 26; }
+    if chars.iter().any(|c| c.is_ascii() && !c.is_ascii_alphanumeric()) { size += 33; }
+    if chars.iter().any(|c| !c.is_ascii()) { size += 100; }
+    size.max(1)
+}
+
+fn find_matches(chars: &[char]) -> Vec<Match> {
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let mut matches = Vec::new();
+    repeats(chars, &mut matches);
+    sequences(&lower, &mut matches);
+    keyboard_walks(&lower, &mut matches);
+    dates(chars, &mut matches);
+    common_words(chars, &lower, &mut matches);
+    matches
+}
+
+/// `aaaa` and repeated blocks such as `1212` or `abcabc`.
+fn repeats(chars: &[char], out: &mut Vec<Match>) {
+    let n = chars.len();
+    for i in 0..n {
+        for k in 1..=(n - i) / 2 {
+            let block = &chars[i..i + k];
+            let mut reps = 1;
+            while i + (reps + 1) * k <= n && chars[i + reps * k..i + (reps + 1) * k] == *block {
+                reps += 1;
+                if k == 1 && reps < 3 {
+                    continue;
+                }
+                let block_bits = k as f64 * (charset_size(block) as f64).log2();
+                out.push(Match { start: i, end: i + reps * k, bits: block_bits + (reps as f64).log2(), kind: Weakness::Repeat });
+            }
+        }
+    }
+}
+
+/// `1234`, `abcd`, `9876`.
+fn sequences(lower: &[char], out: &mut Vec<Match>) {
+    let n = lower.len();
+    for i in 0..n {
+        for step in [1i64, -1] {
+            let mut end = i + 1;
+            while end < n
+                && lower[end] as i64 - lower[end - 1] as i64 == step
+                && class_size(lower[end]) == class_size(lower[i])
+                && lower[end].is_ascii_alphanumeric()
+            {
+                end += 1;
+                if end - i >= 3 {
+                    let bits = f64::from(class_size(lower[i])).log2() + ((end - i) as f64).log2() + 1.0;
+                    out.push(Match { start: i, end, bits, kind: Weakness::Sequence });
+                }
+            }
+        }
+    }
+}
+
+/// Runs along one keyboard row in either direction, four keys or more.
+fn keyboard_walks(lower: &[char], out: &mut Vec<Match>) {
+    // Starting key, row and direction
+    let start_bits = ((KEYBOARD_ROWS.iter().map(|r| r.len()).sum::<usize>() * 2) as f64).log2();
+    let n = lower.len();
+    for row in KEYBOARD_ROWS {
+        let forward: Vec<char> = row.chars().collect();
+        let backward: Vec<char> = row.chars().rev().collect();
+        for keys in [forward, backward] {
+            for i in 0..n {
+                let Some(pos) = keys.iter().position(|&k| k == lower[i]) else { continue };
+                let mut len = 1;
+                while i + len < n && pos + len < keys.len() && lower[i + len] == keys[pos + len] {
+                    len += 1;
+                    if len >= 4 {
+                        out.push(Match { start: i, end: i + len, bits: start_bits + (len as f64).log2(), kind: Weakness::KeyboardWalk });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// All-digit dates: MMDD / DDMM / YYYY, then six and eight digit day-month-year orders.
+fn dates(chars: &[char], out: &mut Vec<Match>) {
+    let n = chars.len();
+    for len in [4, 6, 8] {
+        for i in 0..n.saturating_sub(len - 1) {
+            let window = &chars[i..i + len];
+            if !window.iter().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let num = |a: usize, b: usize| window[a..b].iter().fold(0u32, |acc, c| acc * 10 + c.to_digit(10).unwrap());
+            let day_month = |d: u32, m: u32| (1..=31).contains(&d) && (1..=12).contains(&m);
+            let year = |y: u32| (1900..=2099).contains(&y);
+
+            let bits = match len {
+                4 if year(num(0, 4)) => Some(200f64.log2()),
+                4 if day_month(num(0, 2), num(2, 4)) || day_month(num(2, 4), num(0, 2)) => Some(366f64.log2()),
+                6 if day_month(num(0, 2), num(2, 4)) || day_month(num(2, 4), num(0, 2)) || day_month(num(4, 6), num(2, 4)) => {
+                    Some((366.0f64 * 100.0).log2())
+                }
+                8 if (year(num(4, 8)) && (day_month(num(0, 2), num(2, 4)) || day_month(num(2, 4), num(0, 2))))
+                    || (year(num(0, 4)) && day_month(num(6, 8), num(4, 6))) =>
+                {
+                    Some((366.0f64 * 200.0).log2())
+                }
+                _ => None,
+            };
+            if let Some(bits) = bits {
+                out.push(Match { start: i, end: i + len, bits, kind: Weakness::Date });
+            }
+        }
+    }
+}
+
+fn common() -> &'static HashMap<&'static str, usize> {
+    static WORDS: OnceLock<HashMap<&'static str, usize>> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        COMMON.lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .enumerate()
+            .map(|(rank, word)| (word, rank))
+            .collect()
+    })
+}
+
+fn unleet(c: char) -> char {
+    match c {
+        '0' => 'o',
+        '1' | '!' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        c => c,
+    }
+}
+
+/// Entries of the bundled list, also capitalized or in simple leetspeak (`p@ssw0rd`).
+fn common_words(chars: &[char], lower: &[char], out: &mut Vec<Match>) {
+    let words = common();
+    let n = lower.len();
+    for i in 0..n {
+        for end in (i + 3)..=n.min(i + MAX_WORD_LEN) {
+            let plain: String = lower[i..end].iter().collect();
+            let unleeted: String = lower[i..end].iter().map(|&c| unleet(c)).collect();
+            let (rank, leet) = match (words.get(plain.as_str()), words.get(unleeted.as_str())) {
+                (Some(&rank), _) => (rank, false),
+                (None, Some(&rank)) => (rank, true),
+                (None, None) => continue,
+            };
+            let capitalized = chars[i..end].iter().any(|c| c.is_uppercase());
+            let bits = 1.0 + ((rank + 1) as f64).log2() + f64::from(u8::from(capitalized)) + f64::from(u8::from(leet));
+            out.push(Match { start: i, end, bits, kind: Weakness::CommonWord });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reasons(secret: &str) -> Vec<Weakness> {
+        estimate(secret).reasons
+    }
+
+    #[test]
+    fn test_patterns_are_recognised() {
+        assert!(reasons("aaaaaaa").contains(&Weakness::Repeat));
+        assert!(reasons("47474747").contains(&Weakness::Repeat));
+        assert!(reasons("3456789").contains(&Weakness::Sequence));
+        assert!(reasons("zyxwvut").contains(&Weakness::Sequence));
+        assert!(reasons("asdfghjk").contains(&Weakness::KeyboardWalk));
+        assert!(reasons("25121990").contains(&Weakness::Date));
+        assert!(reasons("19900412").contains(&Weakness::Date));
+        assert!(reasons("P@ssw0rd").contains(&Weakness::CommonWord));
+        assert_eq!(reasons("1234"), [Weakness::Short, Weakness::CommonWord]);
+    }
+
+    #[test]
+    fn test_scores() {
+        for weak in ["", "1234", "0000", "123456", "111111", "qwerty", "password1"] {
+            assert_eq!(estimate(weak).score, 0, "{weak}");
+        }
+        // A full date still takes a day-by-day search
+        assert!(estimate("25121990").entropy_bits < 20.0);
+        // Random PINs: 4 digits ~13 bits, 6 digits ~20 bits
+        assert_eq!(estimate("5829").score, 1);
+        assert!(estimate("582914").entropy_bits > 19.0);
+        assert_eq!(estimate("correct horse battery staple").score, 4);
+        assert_eq!(estimate("Xk9#mQ2$vL7!").score, 4);
+        assert!(estimate("Xk9#mQ2$vL7!").reasons.is_empty());
+        assert!(estimate(&"ab".repeat(128)).reasons.contains(&Weakness::Repeat));
+    }
+
+    #[test]
+    fn test_policy_check() {
+        let policy = StrengthPolicy::RECOMMENDED;
+        assert_eq!(policy.check("5829").unwrap_err(), RichieSafeError::WeakSecret("shorter than 6 characters".into()));
+        assert!(matches!(policy.check("123456"), Err(RichieSafeError::WeakSecret(r)) if r.contains("common_word")));
+        assert!(matches!(policy.check("121212"), Err(RichieSafeError::WeakSecret(_))));
+        assert!(policy.check("582914").is_ok());
+        assert!(StrengthPolicy::NONE.check("1").is_ok());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::crypto::{rng, subkey};
+use crate::crypto::aead::AeadAlgorithm;
+use crate::crypto::suite::CipherSuite;
+use crate::error::{Result, RichieSafeError};
+use uuid::Uuid;
+use zeroize::Zeroize;
+use std::convert::TryInto;
+
+pub const ATTACHMENT_MAGIC: &[u8; 8] = b"RSAFEAT\0";
+/// magic(8) + aead id(1) + attachment id(16) + nonce(24)
+pub const ATTACHMENT_PREFIX_SIZE: usize = 49;
+/// magic, aead id and attachment id: the part of the prefix bound as AAD
+const AAD_SIZE: usize = 25;
+const SUBKEY_INFO: &[u8] = b"richiesafe/attachment/v1/";
+
+/// Per-attachment key: HKDF(vault_key, "richiesafe/attachment/v1/" || id).
+pub fn derive_attachment_key(vault_key: &[u8; 32], id: &Uuid) -> [u8; 32] {
+    let mut info = Vec::with_capacity(SUBKEY_INFO.len() + 16);
+    info.extend_from_slice(SUBKEY_INFO);
+    info.extend_from_slice(id.as_bytes());
+    subkey::derive_subkey(vault_key, &info)
+}
+
+/// Encrypts one attachment into a standalone blob with the vault's AEAD, whose id is
+/// recorded in the prefix. The algorithm and attachment ids are bound as AAD so blobs
+/// can neither be swapped between attachments nor relabelled to another algorithm.
+pub fn seal(suite: CipherSuite, vault_key: &[u8; 32], id: &Uuid, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut key = derive_attachment_key(vault_key, id);
+    let nonce: [u8; 24] = rng::generate_bytes(24).try_into().unwrap();
+
+    let mut prefix = Vec::with_capacity(ATTACHMENT_PREFIX_SIZE);
+    prefix.extend_from_slice(ATTACHMENT_MAGIC);
+    prefix.push(suite.aead.id());
+    prefix.extend_from_slice(id.as_bytes());
+
+    let ciphertext = suite.encrypt(&key, &nonce, plaintext, &prefix);
+    key.zeroize();
+
+    let mut blob = prefix;
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext?);
+    Ok(blob)
+}
+
+/// Reads the attachment id from a blob without decrypting it.
+pub fn peek_id(blob: &[u8]) -> Result<Uuid> {
+    if blob.len() < ATTACHMENT_PREFIX_SIZE {
+        return Err(RichieSafeError::TruncatedBlob);
+    }
+    if &blob[0..8] != ATTACHMENT_MAGIC {
+        return Err(RichieSafeError::CorruptedHeader("invalid attachment magic"));
+    }
+    Ok(Uuid::from_bytes(blob[9..25].try_into().unwrap()))
+}
+
+/// Decrypts with the AEAD named in the blob, so attachments sealed before a vault
+/// changed algorithms stay readable.
+pub fn open(vault_key: &[u8; 32], id: &Uuid, blob: &[u8]) -> Result<Vec<u8>> {
+    if peek_id(blob)? != *id {
+        return Err(RichieSafeError::AttachmentDecryption);
+    }
+    let aead = AeadAlgorithm::from_id(blob[8])?;
+
+    let nonce: [u8; 24] = blob[AAD_SIZE..ATTACHMENT_PREFIX_SIZE].try_into().unwrap();
+    let mut key = derive_attachment_key(vault_key, id);
+    let plaintext = aead.decrypt(&key, &nonce, &blob[ATTACHMENT_PREFIX_SIZE..], &blob[..AAD_SIZE])
+        .map_err(|_| RichieSafeError::AttachmentDecryption);
+    key.zeroize();
+    plaintext
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GCM_SIV: CipherSuite = CipherSuite { aead: AeadAlgorithm::Aes256GcmSiv, ..CipherSuite::DEFAULT };
+
+    #[test]
+    fn test_attachment_roundtrip_and_binding() {
+        let vault_key = [7u8; 32];
+        let id = Uuid::new_v4();
+        let blob = seal(CipherSuite::DEFAULT, &vault_key, &id, b"photo bytes").unwrap();
+
+        assert_eq!(peek_id(&blob).unwrap(), id);
+        assert_eq!(open(&vault_key, &id, &blob).unwrap(), b"photo bytes");
+
+        // Wrong vault key
+        assert_eq!(open(&[8u8; 32], &id, &blob), Err(RichieSafeError::AttachmentDecryption));
+
+        // Blob relabelled as a different attachment
+        let other = Uuid::new_v4();
+        let mut relabelled = blob.clone();
+        relabelled[9..25].copy_from_slice(other.as_bytes());
+        assert_eq!(open(&vault_key, &other, &relabelled), Err(RichieSafeError::AttachmentDecryption));
+
+        // Keys are independent per attachment
+        assert_ne!(derive_attachment_key(&vault_key, &id), derive_attachment_key(&vault_key, &other));
+    }
+
+    #[test]
+    fn test_attachment_follows_suite() {
+        let vault_key = [7u8; 32];
+        let id = Uuid::new_v4();
+
+        for suite in [CipherSuite::DEFAULT, GCM_SIV] {
+            let blob = seal(suite, &vault_key, &id, b"scan").unwrap();
+            assert_eq!(blob[8], suite.aead.id());
+            assert_eq!(open(&vault_key, &id, &blob).unwrap(), b"scan");
+
+            // Relabelling the algorithm breaks the AAD
+            let mut relabelled = blob.clone();
+            relabelled[8] = if suite == GCM_SIV { AeadAlgorithm::XChaCha20Poly1305.id() } else { AeadAlgorithm::Aes256GcmSiv.id() };
+            assert_eq!(open(&vault_key, &id, &relabelled), Err(RichieSafeError::AttachmentDecryption));
+        }
+
+        let mut unknown = seal(CipherSuite::DEFAULT, &vault_key, &id, b"scan").unwrap();
+        unknown[8] = 0x7F;
+        assert_eq!(open(&vault_key, &id, &unknown), Err(RichieSafeError::UnsupportedAead(0x7F)));
+    }
+}
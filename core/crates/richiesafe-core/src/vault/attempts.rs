@@ -0,0 +1,380 @@
+//! Failed-unlock throttling.
+//!
+//! An `AttemptRecord` counts consecutive wrong secrets for one vault and is kept by
+//! the host next to the vault blob. Serialized records are MAC'd under a
+//! host-supplied device key (Keystore / Keychain on mobile) and bound to the vault's
+//! `header_salt`, so edited, corrupted or swapped records are detected. Deleting a
+//! record cannot be detected here, and neither can replaying an older one: the MAC
+//! has no counter to compare against, so restoring a copy saved before a run of
+//! failures resets the count to what it was then. Hosts that need more must keep
+//! the record where the attacker cannot write (e.g. a hardware-backed monotonic
+//! counter) and treat a mismatch as tampering.
+//!
+//! This guards the app's own unlock path only. Someone who copies the blob can
+//! still guess offline; against that the Argon2id cost is the defence.
+
+use crate::crypto::subkey;
+use crate::error::{Result, RichieSafeError};
+use crate::vault::format;
+use crate::vault::header::UnlockMethodType;
+use crate::vault::ops::{self, VaultHandle};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use zeroize::Zeroizing;
+
+pub const RECORD_MAGIC: &[u8; 8] = b"RSAFETRY";
+/// magic(8) + version(1) + vault id(16) + failures(4) + last failure(8) + HMAC(32)
+pub const RECORD_SIZE: usize = 69;
+const RECORD_VERSION: u8 = 1;
+const MAC_INFO: &[u8] = b"richiesafe/attempts/v1";
+
+/// What happens once `ThrottlePolicy::max_failures` is reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockoutAction {
+    /// Every secret is refused until the host resets the record.
+    LockOut,
+    /// PINs are refused; the recovery phrase still unlocks and resets the counter.
+    RequireRecovery,
+    /// Like `RequireRecovery`, and that recovery unlock also removes every PIN
+    /// method from the vault. The user then sets a new one with `ops::reset_pin`.
+    /// (PIN methods can only be rewritten once the vault key is known.)
+    WipePin,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThrottlePolicy {
+    /// Failures allowed before any delay.
+    pub free_attempts: u32,
+    /// Delay after the first failure past `free_attempts`; doubles with each further one.
+    pub base_delay_secs: u64,
+    pub max_delay_secs: u64,
+    /// Failures after which `action` applies; `None` only ever delays.
+    pub max_failures: Option<u32>,
+    pub action: LockoutAction,
+}
+
+/// 3 free attempts, then 30 s doubling up to an hour; recovery phrase required after 10.
+impl Default for ThrottlePolicy {
+    fn default() -> Self {
+        Self {
+            free_attempts: 3,
+            base_delay_secs: 30,
+            max_delay_secs: 60 * 60,
+            max_failures: Some(10),
+            action: LockoutAction::RequireRecovery,
+        }
+    }
+}
+
+impl ThrottlePolicy {
+    /// Delay owed after `failures` consecutive failures.
+    pub fn delay_secs(&self, failures: u32) -> u64 {
+        let Some(doublings) = failures.checked_sub(self.free_attempts + 1) else {
+            return 0;
+        };
+        let factor = 1u64.checked_shl(doublings).unwrap_or(u64::MAX);
+        self.base_delay_secs.saturating_mul(factor).min(self.max_delay_secs)
+    }
+
+    fn tripped(&self, failures: u32) -> bool {
+        self.max_failures.is_some_and(|max| failures >= max)
+    }
+}
+
+/// What the UI should offer for the next attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttemptState {
+    Open,
+    /// Seconds to wait before the next attempt is accepted.
+    Delayed(u64),
+    /// Only the recovery phrase is accepted.
+    RecoveryOnly,
+    LockedOut,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttemptRecord {
+    vault_id: [u8; 16],
+    failures: u32,
+    /// Unix seconds of the last failure
+    last_failure: i64,
+}
+
+/// A successful guarded unlock.
+pub struct Unlocked {
+    pub handle: VaultHandle,
+    /// Set when `LockoutAction::WipePin` removed the PIN methods; persist it in
+    /// place of the old blob.
+    pub resealed: Option<Vec<u8>>,
+}
+
+fn vault_id(vault_blob: &[u8]) -> Result<[u8; 16]> {
+    Ok(format::split(vault_blob)?.0.fixed.header_salt)
+}
+
+fn record_mac(device_key: &[u8], bytes: &[u8]) -> Hmac<Sha256> {
+    // Device keys may be any length; HKDF wants a fixed-size input for derive_subkey
+    let ikm: [u8; 32] = Sha256::digest(device_key).into();
+    let key = Zeroizing::new(subkey::derive_subkey(&ikm, MAC_INFO));
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_ref()).expect("HMAC takes any key length");
+    mac.update(bytes);
+    mac
+}
+
+impl AttemptRecord {
+    /// Fresh record with no failures.
+    pub fn new(vault_blob: &[u8]) -> Result<Self> {
+        Ok(Self { vault_id: vault_id(vault_blob)?, failures: 0, last_failure: 0 })
+    }
+
+    /// A record already at the policy limit, for hosts that fail closed when a
+    /// stored record is `AttemptRecordTampered`.
+    pub fn tripped(vault_blob: &[u8], policy: &ThrottlePolicy) -> Result<Self> {
+        let mut record = Self::new(vault_blob)?;
+        record.failures = policy.max_failures.unwrap_or(policy.free_attempts + 1);
+        record.last_failure = Utc::now().timestamp();
+        Ok(record)
+    }
+
+    /// Reads a record written by `to_bytes` for the vault in `vault_blob`.
+    pub fn from_bytes(bytes: &[u8], device_key: &[u8], vault_blob: &[u8]) -> Result<Self> {
+        if bytes.len() != RECORD_SIZE || &bytes[0..8] != RECORD_MAGIC || bytes[8] != RECORD_VERSION {
+            return Err(RichieSafeError::AttemptRecordTampered);
+        }
+        let (signed, mac) = bytes.split_at(RECORD_SIZE - 32);
+        record_mac(device_key, signed)
+            .verify_slice(mac)
+            .map_err(|_| RichieSafeError::AttemptRecordTampered)?;
+
+        let record = Self {
+            vault_id: bytes[9..25].try_into().unwrap(),
+            failures: u32::from_le_bytes(bytes[25..29].try_into().unwrap()),
+            last_failure: i64::from_le_bytes(bytes[29..37].try_into().unwrap()),
+        };
+        if record.vault_id != vault_id(vault_blob)? {
+            return Err(RichieSafeError::AttemptRecordTampered);
+        }
+        Ok(record)
+    }
+
+    pub fn to_bytes(&self, device_key: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(RECORD_SIZE);
+        buf.extend_from_slice(RECORD_MAGIC);
+        buf.push(RECORD_VERSION);
+        buf.extend_from_slice(&self.vault_id);
+        buf.extend_from_slice(&self.failures.to_le_bytes());
+        buf.extend_from_slice(&self.last_failure.to_le_bytes());
+        let mac = record_mac(device_key, &buf).finalize().into_bytes();
+        buf.extend_from_slice(&mac);
+        buf
+    }
+
+    /// Consecutive failures since the last successful unlock.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Clears the counter, e.g. after an out-of-band re-authentication.
+    pub fn reset(&mut self) {
+        self.failures = 0;
+        self.last_failure = 0;
+    }
+
+    pub fn state(&self, policy: &ThrottlePolicy, now: DateTime<Utc>) -> AttemptState {
+        let tripped = policy.tripped(self.failures);
+        if tripped && policy.action == LockoutAction::LockOut {
+            return AttemptState::LockedOut;
+        }
+
+        // A clock set back gives no head start: elapsed time never goes negative
+        let elapsed = u64::try_from(now.timestamp() - self.last_failure).unwrap_or(0);
+        let remaining = policy.delay_secs(self.failures).saturating_sub(elapsed);
+        if remaining > 0 {
+            AttemptState::Delayed(remaining)
+        } else if tripped {
+            AttemptState::RecoveryOnly
+        } else {
+            AttemptState::Open
+        }
+    }
+
+    fn fail(&mut self, now: DateTime<Utc>) {
+        self.failures = self.failures.saturating_add(1);
+        self.last_failure = now.timestamp().max(self.last_failure);
+    }
+}
+
+/// [`unlock_at`] with the current time.
+pub fn unlock(
+    vault_blob: &[u8],
+    secret: &str,
+    record: &mut AttemptRecord,
+    policy: &ThrottlePolicy,
+) -> Result<Unlocked> {
+    unlock_at(vault_blob, secret, record, policy, Utc::now())
+}
+
+/// Unlocks through the throttle. `record` is updated in place and must be
+/// persisted by the caller whatever the outcome. Only wrong secrets count as
+/// failures; a damaged blob does not.
+pub fn unlock_at(
+    vault_blob: &[u8],
+    secret: &str,
+    record: &mut AttemptRecord,
+    policy: &ThrottlePolicy,
+    now: DateTime<Utc>,
+) -> Result<Unlocked> {
+    if record.vault_id != vault_id(vault_blob)? {
+        return Err(RichieSafeError::AttemptRecordTampered);
+    }
+
+    let recovery_only = match record.state(policy, now) {
+        AttemptState::LockedOut => return Err(RichieSafeError::LockedOut),
+        AttemptState::Delayed(secs) => return Err(RichieSafeError::Throttled(secs)),
+        AttemptState::RecoveryOnly => true,
+        AttemptState::Open => false,
+    };
+
+    let result = if recovery_only {
+        ops::unlock_with_method(vault_blob, UnlockMethodType::Recovery, secret)
+    } else {
+        ops::unlock_vault(vault_blob, secret)
+    };
+
+    match result {
+        Ok(mut handle) => {
+            let resealed = if recovery_only && policy.action == LockoutAction::WipePin {
+                Some(ops::remove_pin_methods(&mut handle)?)
+            } else {
+                None
+            };
+            record.reset();
+            Ok(Unlocked { handle, resealed })
+        }
+        Err(RichieSafeError::WrongSecret) => {
+            record.fail(now);
+            Err(if recovery_only { RichieSafeError::RecoveryRequired } else { RichieSafeError::WrongSecret })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::kdf::KdfParams;
+    use crate::vault::header::VaultType;
+    use chrono::Duration;
+
+    const DEVICE_KEY: &[u8] = b"device key from the keystore";
+
+    fn policy(action: LockoutAction) -> ThrottlePolicy {
+        ThrottlePolicy { free_attempts: 1, base_delay_secs: 10, max_delay_secs: 25, max_failures: Some(3), action }
+    }
+
+    fn new_blob() -> Vec<u8> {
+        ops::create_vault(VaultType::Real, "1234", "recovery", KdfParams::FLOOR, KdfParams::FLOOR).unwrap()
+    }
+
+    #[test]
+    fn test_delays_double_and_cap() {
+        let p = policy(LockoutAction::LockOut);
+        let delays: Vec<u64> = (0..6).map(|n| p.delay_secs(n)).collect();
+        assert_eq!(delays, [0, 0, 10, 20, 25, 25]);
+        assert_eq!(ThrottlePolicy { max_delay_secs: u64::MAX, ..p }.delay_secs(u32::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_wrong_secrets_are_delayed_and_success_resets() {
+        let blob = new_blob();
+        let p = ThrottlePolicy { max_failures: None, ..policy(LockoutAction::LockOut) };
+        let mut record = AttemptRecord::new(&blob).unwrap();
+        let t0 = Utc::now();
+
+        assert_eq!(unlock_at(&blob, "0000", &mut record, &p, t0).err(), Some(RichieSafeError::WrongSecret));
+        assert_eq!(unlock_at(&blob, "0000", &mut record, &p, t0).err(), Some(RichieSafeError::WrongSecret));
+        assert_eq!(record.state(&p, t0), AttemptState::Delayed(10));
+
+        // Even the right PIN waits, and the refusal does not count as a failure
+        assert_eq!(unlock_at(&blob, "1234", &mut record, &p, t0 + Duration::seconds(4)).err(), Some(RichieSafeError::Throttled(6)));
+        assert_eq!(record.failures(), 2);
+        // Setting the clock back does not shorten the wait
+        assert_eq!(record.state(&p, t0 - Duration::days(1)), AttemptState::Delayed(10));
+
+        assert!(unlock_at(&blob, "1234", &mut record, &p, t0 + Duration::seconds(10)).is_ok());
+        assert_eq!(record.failures(), 0);
+    }
+
+    #[test]
+    fn test_record_is_tamper_evident() {
+        let blob = new_blob();
+        let mut record = AttemptRecord::new(&blob).unwrap();
+        record.fail(Utc::now());
+        let bytes = record.to_bytes(DEVICE_KEY);
+        assert_eq!(bytes.len(), RECORD_SIZE);
+        assert_eq!(AttemptRecord::from_bytes(&bytes, DEVICE_KEY, &blob).unwrap(), record);
+
+        for i in 0..RECORD_SIZE {
+            let mut edited = bytes.clone();
+            edited[i] ^= 0x01;
+            assert_eq!(AttemptRecord::from_bytes(&edited, DEVICE_KEY, &blob).err(), Some(RichieSafeError::AttemptRecordTampered));
+        }
+        assert_eq!(AttemptRecord::from_bytes(&bytes, b"other device", &blob).err(), Some(RichieSafeError::AttemptRecordTampered));
+        assert_eq!(AttemptRecord::from_bytes(&bytes, DEVICE_KEY, &new_blob()).err(), Some(RichieSafeError::AttemptRecordTampered));
+    }
+
+    /// Runs three wrong PINs past their delays so `policy(action)` trips.
+    fn trip(blob: &[u8], action: LockoutAction) -> (AttemptRecord, DateTime<Utc>) {
+        let p = policy(action);
+        let mut record = AttemptRecord::new(blob).unwrap();
+        let mut now = Utc::now();
+        for _ in 0..3 {
+            now += Duration::seconds(p.max_delay_secs as i64);
+            assert_eq!(unlock_at(blob, "0000", &mut record, &p, now).err(), Some(RichieSafeError::WrongSecret));
+        }
+        (record, now + Duration::seconds(p.max_delay_secs as i64))
+    }
+
+    #[test]
+    fn test_lock_out_refuses_everything() {
+        let blob = new_blob();
+        let (mut record, later) = trip(&blob, LockoutAction::LockOut);
+        let p = policy(LockoutAction::LockOut);
+        assert_eq!(record.state(&p, later), AttemptState::LockedOut);
+        assert_eq!(unlock_at(&blob, "1234", &mut record, &p, later).err(), Some(RichieSafeError::LockedOut));
+        assert_eq!(unlock_at(&blob, "recovery", &mut record, &p, later).err(), Some(RichieSafeError::LockedOut));
+    }
+
+    #[test]
+    fn test_require_recovery_accepts_only_the_phrase() {
+        let blob = new_blob();
+        let (mut record, later) = trip(&blob, LockoutAction::RequireRecovery);
+        let p = policy(LockoutAction::RequireRecovery);
+        assert_eq!(record.state(&p, later), AttemptState::RecoveryOnly);
+
+        assert_eq!(unlock_at(&blob, "1234", &mut record, &p, later).err(), Some(RichieSafeError::RecoveryRequired));
+        let later = later + Duration::seconds(p.max_delay_secs as i64);
+        let unlocked = unlock_at(&blob, "recovery", &mut record, &p, later).unwrap();
+        assert_eq!(unlocked.handle.unlocked_via(), UnlockMethodType::Recovery);
+        assert!(unlocked.resealed.is_none());
+        assert_eq!(record.state(&p, later), AttemptState::Open);
+    }
+
+    #[test]
+    fn test_wipe_pin_removes_pin_methods_on_recovery() {
+        let blob = new_blob();
+        let (mut record, later) = trip(&blob, LockoutAction::WipePin);
+        let p = policy(LockoutAction::WipePin);
+
+        let mut unlocked = unlock_at(&blob, "recovery", &mut record, &p, later).unwrap();
+        let resealed = unlocked.resealed.take().unwrap();
+        assert_eq!(ops::unlock_vault(&resealed, "1234").err(), Some(RichieSafeError::WrongSecret));
+
+        // The record still matches the resealed vault, and a new PIN can be set
+        let mut record = AttemptRecord::from_bytes(&record.to_bytes(DEVICE_KEY), DEVICE_KEY, &resealed).unwrap();
+        let with_pin = ops::reset_pin(&mut unlocked.handle, "5678", KdfParams::FLOOR).unwrap();
+        assert!(unlock_at(&with_pin, "5678", &mut record, &p, later).is_ok());
+    }
+}
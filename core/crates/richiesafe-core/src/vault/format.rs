@@ -1,4 +1,5 @@
-use crate::vault::header::{VaultHeader, HEADER_SIZE};
+use crate::vault::header::VaultHeader;
+use crate::error::{Result, RichieSafeError};
 use std::convert::TryInto;
 
 pub fn assemble(header: &VaultHeader, body_nonce: &[u8; 24], body_ciphertext: &[u8]) -> Vec<u8> {
@@ -12,19 +13,18 @@ pub fn assemble(header: &VaultHeader, body_nonce: &[u8; 24], body_ciphertext: &[
     blob
 }
 
-pub fn split(blob: &[u8]) -> Result<(VaultHeader, &[u8; 24], &[u8]), String> {
-    if blob.len() < HEADER_SIZE + 24 {
-        return Err("Blob too short".into());
+pub fn split(blob: &[u8]) -> Result<(VaultHeader, &[u8; 24], &[u8])> {
+    let header_len = VaultHeader::peek_len(blob)?;
+    let body_start = header_len.checked_add(24).ok_or(RichieSafeError::TruncatedBlob)?;
+    if blob.len() < body_start {
+        return Err(RichieSafeError::TruncatedBlob);
     }
 
-    let header = VaultHeader::parse(&blob[..HEADER_SIZE])?;
-    let header_len = HEADER_SIZE; 
+    let header = VaultHeader::parse(&blob[..header_len])?;
     
-    // Previous check ensures this is safe
-    // if blob.len() < header_len + 24 { ... }
-    
-    let body_nonce: &[u8; 24] = blob[header_len..header_len+24].try_into().map_err(|_| "Invalid nonce")?;
-    let body_ciphertext = &blob[header_len+24..];
+    let body_nonce: &[u8; 24] = blob[header_len..body_start].try_into()
+        .map_err(|_| RichieSafeError::TruncatedBlob)?;
+    let body_ciphertext = &blob[body_start..];
     
     Ok((header, body_nonce, body_ciphertext))
 }
@@ -1,10 +1,32 @@
 use crate::crypto::kdf::KdfParams;
-use crate::crypto::rng;
+use crate::crypto::suite::CipherSuite;
+use crate::crypto::{rng, subkey};
+use crate::error::{Result, RichieSafeError};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
 use std::convert::TryInto;
+use zeroize::Zeroizing;
 
 pub const MAGIC: &[u8; 8] = b"RSAFEV1\0";
-pub const HEADER_SIZE: usize = 234;
+pub const VERSION_V1: u16 = 1;
+pub const VERSION_V2: u16 = 2;
+/// v1 headers are always 32 fixed bytes + exactly 2 methods.
+pub const V1_HEADER_SIZE: usize = 234;
+pub const V1_FIXED_SIZE: usize = 32;
+/// v2 appends a u32 total header length to the v1 fixed part.
+pub const V2_FIXED_SIZE: usize = 36;
 pub const METHOD_SIZE: usize = 101;
+pub const MAX_UNLOCK_METHODS: usize = 16;
+/// Upper bound on the v2 length field, far above any header this crate writes,
+/// so an attacker-chosen length can neither overflow offsets nor force huge slices.
+pub const MAX_V2_HEADER_SIZE: usize = 64 * 1024;
+/// v2 flag: the header ends with an authentication trailer. Every v2 header must
+/// carry it; one without is treated as tampered rather than as an older layout.
+pub const FLAG_HEADER_AUTH: u16 = 0x0001;
+/// Trailer of authenticated v2 headers: HMAC-SHA256 under a vault-key subkey,
+/// then SHA-256 over everything before it (header and MAC).
+pub const HEADER_AUTH_SIZE: usize = 64;
+const HEADER_MAC_INFO: &[u8] = b"richiesafe/header-mac/v1";
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum VaultType {
@@ -18,6 +40,16 @@ pub enum UnlockMethodType {
     Recovery = 0x02,
 }
 
+impl UnlockMethodType {
+    /// Lowercase name used in errors and by the wasm layer.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pin => "pin",
+            Self::Recovery => "recovery",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HeaderFixed {
     pub version: u16,
@@ -42,27 +74,155 @@ pub struct UnlockMethod {
 pub struct VaultHeader {
     pub fixed: HeaderFixed,
     pub methods: Vec<UnlockMethod>,
+    /// Set by `authenticate`, or read from an authenticated v2 header.
+    pub mac: Option<[u8; 32]>,
+}
+
+impl UnlockMethod {
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.push(self.method_id as u8);
+        buf.extend_from_slice(&self.kdf_params.m_cost.to_le_bytes());
+        buf.extend_from_slice(&self.kdf_params.t_cost.to_le_bytes());
+        buf.extend_from_slice(&self.kdf_params.p_cost.to_le_bytes());
+        buf.extend_from_slice(&self.method_salt);
+        buf.extend_from_slice(&self.wrap_nonce);
+        buf.extend_from_slice(&self.wrapped_key);
+    }
+
+    fn parse(m_bytes: &[u8]) -> Result<Self> {
+        if m_bytes.len() != METHOD_SIZE {
+            return Err(RichieSafeError::CorruptedHeader("invalid method size"));
+        }
+
+        let m_id = match m_bytes[0] {
+            0x01 => UnlockMethodType::Pin,
+            0x02 => UnlockMethodType::Recovery,
+            _ => return Err(RichieSafeError::CorruptedHeader("invalid method type")),
+        };
+
+        let m_cost = u32::from_le_bytes(m_bytes[1..5].try_into().unwrap());
+        let t_cost = u32::from_le_bytes(m_bytes[5..9].try_into().unwrap());
+        let p_cost = u32::from_le_bytes(m_bytes[9..13].try_into().unwrap());
+
+        Ok(Self {
+            method_id: m_id,
+            kdf_params: KdfParams { m_cost, t_cost, p_cost },
+            method_salt: m_bytes[13..29].try_into().unwrap(),
+            wrap_nonce: m_bytes[29..53].try_into().unwrap(),
+            wrapped_key: m_bytes[53..101].try_into().unwrap(),
+        })
+    }
 }
 
 impl VaultHeader {
+    /// New headers are always written in the v2 layout.
     pub fn new(vault_type: VaultType, methods: Vec<UnlockMethod>) -> Self {
         Self {
             fixed: HeaderFixed {
-                version: 1,
+                version: VERSION_V2,
                 flags: 0,
                 vault_type,
-                kdf_id: 1, // Argon2id
-                aead_id: 1, // XChaCha20Poly1305
+                kdf_id: CipherSuite::DEFAULT.kdf.id(),
+                aead_id: CipherSuite::DEFAULT.aead.id(),
                 unlock_methods_count: methods.len() as u8,
                 header_salt: rng::generate_bytes(16).try_into().unwrap(),
             },
             methods,
+            mac: None,
+        }
+    }
+
+    pub fn with_suite(mut self, suite: CipherSuite) -> Self {
+        self.fixed.kdf_id = suite.kdf.id();
+        self.fixed.aead_id = suite.aead.id();
+        self
+    }
+
+    /// Algorithms named by `kdf_id` / `aead_id`; unknown ids are an error.
+    pub fn suite(&self) -> Result<CipherSuite> {
+        CipherSuite::from_ids(self.fixed.kdf_id, self.fixed.aead_id)
+    }
+
+    fn has_auth(&self) -> bool {
+        self.fixed.version == VERSION_V2 && self.fixed.flags & FLAG_HEADER_AUTH != 0
+    }
+
+    /// Marks a v2 header as authenticated and computes its MAC under `vault_key`.
+    /// v1 headers have a fixed layout with no room for it and are left unchanged.
+    pub fn authenticate(&mut self, vault_key: &[u8; 32]) {
+        if self.fixed.version != VERSION_V2 {
+            return;
+        }
+        self.fixed.flags |= FLAG_HEADER_AUTH;
+        self.mac = Some(header_mac(vault_key, &self.signed_bytes()).finalize().into_bytes().into());
+    }
+
+    /// Checks the MAC of a v2 header against an unwrapped vault key. v1 headers
+    /// have no MAC and pass; a v2 header without one fails.
+    pub fn verify_mac(&self, vault_key: &[u8; 32]) -> Result<()> {
+        if self.fixed.version == VERSION_V1 {
+            return Ok(());
+        }
+        let mac = self.mac.filter(|_| self.has_auth()).ok_or(RichieSafeError::HeaderTampered)?;
+        header_mac(vault_key, &self.signed_bytes())
+            .verify_slice(&mac)
+            .map_err(|_| RichieSafeError::HeaderTampered)
+    }
+
+    /// Size in bytes of the serialized header.
+    pub fn encoded_len(&self) -> usize {
+        match self.fixed.version {
+            VERSION_V1 => V1_FIXED_SIZE + self.methods.len() * METHOD_SIZE,
+            _ if self.has_auth() => V2_FIXED_SIZE + self.methods.len() * (2 + METHOD_SIZE) + HEADER_AUTH_SIZE,
+            _ => V2_FIXED_SIZE + self.methods.len() * (2 + METHOD_SIZE),
+        }
+    }
+
+    /// Reads the header length from the start of a blob without parsing the methods.
+    pub fn peek_len(blob: &[u8]) -> Result<usize> {
+        if blob.len() < V1_FIXED_SIZE {
+            return Err(RichieSafeError::TruncatedBlob);
+        }
+        if &blob[0..8] != MAGIC {
+            return Err(RichieSafeError::CorruptedHeader("invalid magic"));
+        }
+
+        match u16::from_le_bytes(blob[8..10].try_into().unwrap()) {
+            VERSION_V1 => Ok(V1_HEADER_SIZE),
+            VERSION_V2 => {
+                if blob.len() < V2_FIXED_SIZE {
+                    return Err(RichieSafeError::TruncatedBlob);
+                }
+                let len = u32::from_le_bytes(blob[32..36].try_into().unwrap()) as usize;
+                if len < V2_FIXED_SIZE {
+                    return Err(RichieSafeError::CorruptedHeader("header length too small"));
+                }
+                if len > MAX_V2_HEADER_SIZE {
+                    return Err(RichieSafeError::CorruptedHeader("header length too large"));
+                }
+                if len > blob.len() {
+                    return Err(RichieSafeError::TruncatedBlob);
+                }
+                Ok(len)
+            }
+            v => Err(RichieSafeError::UnsupportedVersion(v)),
         }
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(HEADER_SIZE);
-        
+        let mut buf = self.signed_bytes();
+        if self.has_auth() {
+            buf.extend_from_slice(&self.mac.unwrap_or_default());
+            let commitment = Sha256::digest(&buf);
+            buf.extend_from_slice(&commitment);
+        }
+        buf
+    }
+
+    /// Serialized header without the authentication trailer; this is what the MAC covers.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.encoded_len());
+
         // Fixed: 32 bytes
         buf.extend_from_slice(MAGIC); // 0-7
         buf.extend_from_slice(&self.fixed.version.to_le_bytes()); // 8-9
@@ -70,92 +230,57 @@ impl VaultHeader {
         buf.push(self.fixed.vault_type as u8); // 12
         buf.push(self.fixed.kdf_id); // 13
         buf.push(self.fixed.aead_id); // 14
-        buf.push(self.fixed.unlock_methods_count); // 15
+        buf.push(self.methods.len() as u8); // 15
         buf.extend_from_slice(&self.fixed.header_salt); // 16-31
-        
-        // Methods
+
+        if self.fixed.version == VERSION_V1 {
+            for m in &self.methods {
+                m.write(&mut buf);
+            }
+            return buf;
+        }
+
+        // v2: total header length, then length-prefixed method records
+        buf.extend_from_slice(&(self.encoded_len() as u32).to_le_bytes()); // 32-35
         for m in &self.methods {
-             buf.push(m.method_id as u8);
-             buf.extend_from_slice(&m.kdf_params.m_cost.to_le_bytes());
-             buf.extend_from_slice(&m.kdf_params.t_cost.to_le_bytes());
-             buf.extend_from_slice(&m.kdf_params.p_cost.to_le_bytes());
-             buf.extend_from_slice(&m.method_salt);
-             buf.extend_from_slice(&m.wrap_nonce);
-             buf.extend_from_slice(&m.wrapped_key);
-        }
-        
-        // Sanity Check ensure size (padding shouldn't be needed if logic is correct)
-        if buf.len() != HEADER_SIZE {
-             // If we support variable methods in future we remove check, but for v1 it is fixed.
-             // panic!("Header size mismatch");
+            buf.extend_from_slice(&(METHOD_SIZE as u16).to_le_bytes());
+            m.write(&mut buf);
         }
         buf
     }
 
-    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
-        if bytes.len() < 32 {
-             return Err("Header too short".into());
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < V1_FIXED_SIZE {
+             return Err(RichieSafeError::TruncatedBlob);
         }
         if &bytes[0..8] != MAGIC {
-             return Err("Invalid magic".into());
+             return Err(RichieSafeError::CorruptedHeader("invalid magic"));
         }
-        
+
         let version = u16::from_le_bytes(bytes[8..10].try_into().unwrap());
-        if version != 1 {
-            return Err("Unsupported version".into());
-        }
-        
+
+        // The commitment is checked before any other field is interpreted
+        let (bytes, mac) = match version {
+            VERSION_V1 => (bytes, None),
+            VERSION_V2 => Self::split_v2_auth(bytes)?,
+            _ => return Err(RichieSafeError::UnsupportedVersion(version)),
+        };
+
         let vault_type_byte = bytes[12];
         let vault_type = match vault_type_byte {
             0x01 => VaultType::Real,
             0x02 => VaultType::Decoy,
-            _ => return Err("Invalid vault type".into()),
+            _ => return Err(RichieSafeError::CorruptedHeader("invalid vault type")),
         };
 
         let count = bytes[15];
-        if count != 2 {
-            return Err("V1 header must have exactly 2 methods".into());
-        }
-
-        if bytes.len() != 32 + (count as usize * 101) {
-             return Err("Header size mismatch".into());
-        }
-        
         let header_salt: [u8; 16] = bytes[16..32].try_into().unwrap();
 
-        let mut methods = Vec::new();
-        let mut offset = 32;
-        
-        for _ in 0..count {
-            if offset + 101 > bytes.len() {
-                return Err("Header too short for methods".into());
-            }
-            let m_bytes = &bytes[offset..offset+101];
-            
-            let m_id = match m_bytes[0] {
-                0x01 => UnlockMethodType::Pin,
-                0x02 => UnlockMethodType::Recovery,
-                _ => return Err("Invalid method type".into()),
-            };
-            
-            let m_cost = u32::from_le_bytes(m_bytes[1..5].try_into().unwrap());
-            let t_cost = u32::from_le_bytes(m_bytes[5..9].try_into().unwrap());
-            let p_cost = u32::from_le_bytes(m_bytes[9..13].try_into().unwrap());
-            
-            let method_salt = m_bytes[13..29].try_into().unwrap();
-            let wrap_nonce = m_bytes[29..53].try_into().unwrap();
-            let wrapped_key = m_bytes[53..101].try_into().unwrap();
-
-            methods.push(UnlockMethod {
-                method_id: m_id,
-                kdf_params: KdfParams { m_cost, t_cost, p_cost },
-                method_salt,
-                wrap_nonce,
-                wrapped_key,
-            });
-            
-            offset += 101;
-        }
+        let methods = match version {
+            VERSION_V1 => Self::parse_v1_methods(bytes, count)?,
+            VERSION_V2 => Self::parse_v2_methods(bytes, count)?,
+            _ => return Err(RichieSafeError::UnsupportedVersion(version)),
+        };
 
         Ok(Self {
             fixed: HeaderFixed {
@@ -168,6 +293,214 @@ impl VaultHeader {
                 header_salt,
             },
             methods,
+            mac,
         })
     }
+
+    /// Checks the v2 length field and the commitment. A header without the
+    /// authentication flag is a stripped trailer, not an older layout.
+    /// Returns the bytes covered by the MAC and the stored MAC.
+    fn split_v2_auth(bytes: &[u8]) -> Result<(&[u8], Option<[u8; 32]>)> {
+        if bytes.len() < V2_FIXED_SIZE {
+            return Err(RichieSafeError::TruncatedBlob);
+        }
+
+        let header_len = u32::from_le_bytes(bytes[32..36].try_into().unwrap()) as usize;
+        if bytes.len() != header_len {
+            return Err(RichieSafeError::CorruptedHeader("header size mismatch"));
+        }
+
+        let flags = u16::from_le_bytes(bytes[10..12].try_into().unwrap());
+        if flags & FLAG_HEADER_AUTH == 0 {
+            return Err(RichieSafeError::HeaderTampered);
+        }
+        if bytes.len() < V2_FIXED_SIZE + HEADER_AUTH_SIZE {
+            return Err(RichieSafeError::CorruptedHeader("header too short for authentication"));
+        }
+
+        let (committed, commitment) = bytes.split_at(bytes.len() - 32);
+        if Sha256::digest(committed).as_slice() != commitment {
+            return Err(RichieSafeError::HeaderTampered);
+        }
+        let (signed, mac) = committed.split_at(committed.len() - 32);
+        Ok((signed, Some(mac.try_into().unwrap())))
+    }
+
+    fn parse_v1_methods(bytes: &[u8], count: u8) -> Result<Vec<UnlockMethod>> {
+        if count != 2 {
+            return Err(RichieSafeError::CorruptedHeader("v1 header must have exactly 2 methods"));
+        }
+
+        if bytes.len() != V1_FIXED_SIZE + (count as usize * METHOD_SIZE) {
+             return Err(RichieSafeError::CorruptedHeader("header size mismatch"));
+        }
+
+        bytes[V1_FIXED_SIZE..]
+            .chunks_exact(METHOD_SIZE)
+            .map(UnlockMethod::parse)
+            .collect()
+    }
+
+    fn parse_v2_methods(bytes: &[u8], count: u8) -> Result<Vec<UnlockMethod>> {
+        if count == 0 || count as usize > MAX_UNLOCK_METHODS {
+            return Err(RichieSafeError::CorruptedHeader("invalid unlock method count"));
+        }
+        let mut methods = Vec::with_capacity(count as usize);
+        let mut offset = V2_FIXED_SIZE;
+
+        for _ in 0..count {
+            if offset + 2 > bytes.len() {
+                return Err(RichieSafeError::TruncatedBlob);
+            }
+            let record_len = u16::from_le_bytes(bytes[offset..offset+2].try_into().unwrap()) as usize;
+            offset += 2;
+
+            if offset + record_len > bytes.len() {
+                return Err(RichieSafeError::TruncatedBlob);
+            }
+            methods.push(UnlockMethod::parse(&bytes[offset..offset+record_len])?);
+            offset += record_len;
+        }
+
+        if offset != bytes.len() {
+            return Err(RichieSafeError::CorruptedHeader("trailing bytes after methods"));
+        }
+
+        Ok(methods)
+    }
+}
+
+fn header_mac(vault_key: &[u8; 32], signed: &[u8]) -> Hmac<Sha256> {
+    let key = Zeroizing::new(subkey::derive_subkey(vault_key, HEADER_MAC_INFO));
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_ref()).expect("HMAC takes any key length");
+    mac.update(signed);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn method(method_id: UnlockMethodType, fill: u8) -> UnlockMethod {
+        UnlockMethod {
+            method_id,
+            kdf_params: KdfParams { m_cost: 1024, t_cost: 1, p_cost: 1 },
+            method_salt: [fill; 16],
+            wrap_nonce: [fill; 24],
+            wrapped_key: [fill; 48],
+        }
+    }
+
+    #[test]
+    fn test_v2_roundtrip_with_three_methods() {
+        let mut header = VaultHeader::new(VaultType::Real, vec![
+            method(UnlockMethodType::Pin, 1),
+            method(UnlockMethodType::Pin, 2),
+            method(UnlockMethodType::Recovery, 3),
+        ]);
+        header.authenticate(&[7u8; 32]);
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), header.encoded_len());
+        assert_eq!(VaultHeader::peek_len(&bytes).unwrap(), bytes.len());
+
+        let parsed = VaultHeader::parse(&bytes).unwrap();
+        assert_eq!(parsed.fixed.version, VERSION_V2);
+        assert_eq!(parsed.methods.len(), 3);
+        assert_eq!(parsed.methods[1].method_salt, [2; 16]);
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_v2_single_method() {
+        let mut header = VaultHeader::new(VaultType::Decoy, vec![method(UnlockMethodType::Pin, 7)]);
+        header.authenticate(&[7u8; 32]);
+        let parsed = VaultHeader::parse(&header.to_bytes()).unwrap();
+        assert_eq!(parsed.methods.len(), 1);
+        assert_eq!(parsed.fixed.vault_type, VaultType::Decoy);
+    }
+
+    #[test]
+    fn test_v1_still_parses() {
+        let mut header = VaultHeader::new(VaultType::Real, vec![
+            method(UnlockMethodType::Pin, 1),
+            method(UnlockMethodType::Recovery, 2),
+        ]);
+        header.fixed.version = VERSION_V1;
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), V1_HEADER_SIZE);
+        assert_eq!(VaultHeader::peek_len(&bytes).unwrap(), V1_HEADER_SIZE);
+
+        let parsed = VaultHeader::parse(&bytes).unwrap();
+        assert_eq!(parsed.fixed.version, VERSION_V1);
+        assert_eq!(parsed.methods[0].method_id, UnlockMethodType::Pin);
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_authenticated_header() {
+        let key = [7u8; 32];
+        let mut header = VaultHeader::new(VaultType::Real, vec![
+            method(UnlockMethodType::Pin, 1),
+            method(UnlockMethodType::Recovery, 2),
+        ]);
+        header.authenticate(&key);
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), header.encoded_len());
+        assert_eq!(VaultHeader::peek_len(&bytes).unwrap(), bytes.len());
+
+        let parsed = VaultHeader::parse(&bytes).unwrap();
+        assert_eq!(parsed.mac, header.mac);
+        assert_eq!(parsed.to_bytes(), bytes);
+        assert!(parsed.verify_mac(&key).is_ok());
+        assert_eq!(parsed.verify_mac(&[8u8; 32]), Err(RichieSafeError::HeaderTampered));
+
+        // Any change under the commitment is caught while parsing
+        let mut flipped = bytes.clone();
+        flipped[40] ^= 0x01;
+        assert_eq!(VaultHeader::parse(&flipped).unwrap_err(), RichieSafeError::HeaderTampered);
+
+        // Clearing the flag and stripping the trailer does not downgrade the header
+        let mut stripped = bytes[..bytes.len() - HEADER_AUTH_SIZE].to_vec();
+        stripped[10..12].copy_from_slice(&0u16.to_le_bytes());
+        let stripped_len = stripped.len() as u32;
+        stripped[32..36].copy_from_slice(&stripped_len.to_le_bytes());
+        assert_eq!(VaultHeader::parse(&stripped).unwrap_err(), RichieSafeError::HeaderTampered);
+        let mut unsigned = parsed.clone();
+        unsigned.mac = None;
+        assert_eq!(unsigned.verify_mac(&key), Err(RichieSafeError::HeaderTampered));
+
+        // v1 has no room for a MAC
+        let mut v1 = parsed.clone();
+        v1.fixed.version = VERSION_V1;
+        v1.authenticate(&key);
+        assert_eq!(v1.to_bytes().len(), V1_HEADER_SIZE);
+    }
+
+    #[test]
+    fn test_v2_rejects_bad_layout() {
+        let mut header = VaultHeader::new(VaultType::Real, vec![method(UnlockMethodType::Pin, 1)]);
+        header.authenticate(&[7u8; 32]);
+        let bytes = header.to_bytes();
+
+        let mut no_methods = bytes[..V2_FIXED_SIZE].to_vec();
+        no_methods[15] = 0;
+        no_methods[32..36].copy_from_slice(&(V2_FIXED_SIZE as u32).to_le_bytes());
+        assert!(matches!(VaultHeader::parse(&no_methods), Err(RichieSafeError::CorruptedHeader(_))));
+
+        let mut bad_len = bytes.clone();
+        bad_len[32] ^= 0x01;
+        assert!(matches!(VaultHeader::parse(&bad_len), Err(RichieSafeError::CorruptedHeader(_))));
+
+        let mut huge_len = bytes.clone();
+        huge_len[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(VaultHeader::peek_len(&huge_len), Err(RichieSafeError::CorruptedHeader("header length too large")));
+        assert!(matches!(crate::vault::format::split(&huge_len), Err(RichieSafeError::CorruptedHeader(_))));
+        let mut past_end = bytes.clone();
+        past_end[32..36].copy_from_slice(&((bytes.len() + 1) as u32).to_le_bytes());
+        assert_eq!(VaultHeader::peek_len(&past_end), Err(RichieSafeError::TruncatedBlob));
+
+        let mut bad_version = bytes;
+        bad_version[8] = 9;
+        assert_eq!(VaultHeader::parse(&bad_version).unwrap_err(), RichieSafeError::UnsupportedVersion(9));
+    }
 }
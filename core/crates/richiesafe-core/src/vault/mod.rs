@@ -1,3 +1,5 @@
 pub mod header;
 pub mod format;
 pub mod ops;
+pub mod attachment;
+pub mod attempts;
@@ -1,15 +1,22 @@
 use crate::crypto::{kdf, rng};
-use crate::crypto::aead as crypto_aead;
+use crate::crypto::suite::CipherSuite;
 use crate::vault::header::{self, VaultHeader, UnlockMethod, VaultType, UnlockMethodType};
-use crate::vault::format;
-use crate::models::entry::VaultState;
-use zeroize::Zeroize;
+use crate::vault::{attachment, format};
+use crate::models::entry::{AttachmentRef, EntryKind, VaultEntry, VaultState};
+use crate::models::migration;
+use crate::otp::{OtpCode, OtpKind, OtpSecret};
+use crate::error::{Result, RichieSafeError};
+use crate::strength;
+use zeroize::{Zeroize, Zeroizing};
+use uuid::Uuid;
+use chrono::Utc;
 use std::convert::TryInto;
 
 pub struct VaultHandle {
     pub vault_key: [u8; 32],
     pub state: VaultState,
     pub original_header: VaultHeader,
+    unlocked_via: UnlockMethodType,
 }
 
 impl Drop for VaultHandle {
@@ -18,248 +25,644 @@ impl Drop for VaultHandle {
     }
 }
 
+impl VaultHandle {
+    /// Unlocks `blob` with a PIN or recovery phrase. Counterpart of [`VaultHandle::seal`].
+    pub fn open(blob: &[u8], secret: &str) -> Result<Self> {
+        unlock_vault(blob, secret)
+    }
+
+    /// Which kind of secret opened this handle. A freshly created vault counts as
+    /// opened by PIN. Later PIN changes or rotations do not change it.
+    pub fn unlocked_via(&self) -> UnlockMethodType {
+        self.unlocked_via
+    }
+
+    /// Encrypts the current state under `original_header` and returns the full blob to persist.
+    pub fn seal(&self) -> Result<Vec<u8>> {
+        seal_vault(&self.original_header, &self.vault_key, &self.state)
+    }
+
+    /// Re-encrypts the body under a new header (the AAD changes with it).
+    /// The handle only adopts `header` once sealing succeeded.
+    pub fn reencrypt(&mut self, header: VaultHeader) -> Result<Vec<u8>> {
+        let blob = seal_vault(&header, &self.vault_key, &self.state)?;
+        self.original_header = header;
+        Ok(blob)
+    }
+
+    /// Appends `entry` and returns its id.
+    pub fn add_entry(&mut self, entry: VaultEntry) -> Uuid {
+        let id = entry.id;
+        self.state.entries.push(entry);
+        id
+    }
+
+    pub fn find_by_id(&self, id: &Uuid) -> Option<&VaultEntry> {
+        self.state.entries.iter().find(|e| e.id == *id)
+    }
+
+    pub fn get_entry(&self, id: &Uuid) -> Result<&VaultEntry> {
+        self.find_by_id(id).ok_or(RichieSafeError::EntryNotFound(*id))
+    }
+
+    /// Edits an entry in place. `id` and `created_at` cannot be changed by `edit`;
+    /// `updated_at` is bumped to now.
+    pub fn update_entry<F: FnOnce(&mut VaultEntry)>(&mut self, id: &Uuid, edit: F) -> Result<()> {
+        let entry = self.state.entries.iter_mut()
+            .find(|e| e.id == *id)
+            .ok_or(RichieSafeError::EntryNotFound(*id))?;
+
+        let created_at = entry.created_at;
+        edit(entry);
+        entry.id = *id;
+        entry.created_at = created_at;
+        entry.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /// One-time code of the entry's OTP seed at `unix_secs` (TOTP), or for its
+    /// stored counter (HOTP).
+    pub fn entry_otp_code(&self, id: &Uuid, unix_secs: u64) -> Result<OtpCode> {
+        self.get_entry(id)?.otp.as_ref()
+            .ok_or(RichieSafeError::OtpNotConfigured(*id))?
+            .code_at(unix_secs)
+    }
+
+    /// Returns the HOTP code for the stored counter and moves the counter on, so
+    /// the next call yields the next code. Seal afterwards to keep the counter.
+    pub fn next_hotp_code(&mut self, id: &Uuid) -> Result<OtpCode> {
+        let code = self.entry_otp_code(id, 0)?;
+        let mut advanced = false;
+        self.update_entry(id, |e| {
+            if let Some(OtpSecret { kind: OtpKind::Hotp { counter }, .. }) = &mut e.otp {
+                *counter += 1;
+                advanced = true;
+            }
+        })?;
+        if !advanced {
+            return Err(RichieSafeError::InvalidOtp("not an HOTP seed".to_string()));
+        }
+        Ok(code)
+    }
+
+    pub fn delete_entry(&mut self, id: &Uuid) -> Result<()> {
+        let pos = self.state.entries.iter()
+            .position(|e| e.id == *id)
+            .ok_or(RichieSafeError::EntryNotFound(*id))?;
+        self.state.entries.remove(pos);
+        Ok(())
+    }
+
+    /// Encrypts `data` as a new attachment of `entry_id` under its own derived key.
+    /// The reference is recorded on the entry; the returned blob must be stored by the
+    /// caller under `reference.id`, outside the vault body.
+    pub fn add_attachment(
+        &mut self,
+        entry_id: &Uuid,
+        file_name: &str,
+        mime_type: Option<String>,
+        data: &[u8],
+    ) -> Result<(AttachmentRef, Vec<u8>)> {
+        let reference = AttachmentRef {
+            id: Uuid::new_v4(),
+            file_name: file_name.to_string(),
+            mime_type,
+            size: data.len() as u64,
+        };
+        let blob = attachment::seal(self.original_header.suite()?, &self.vault_key, &reference.id, data)?;
+
+        let stored = reference.clone();
+        self.update_entry(entry_id, |e| e.attachments.push(stored))?;
+        Ok((reference, blob))
+    }
+
+    pub fn attachment_refs(&self) -> impl Iterator<Item = &AttachmentRef> {
+        self.state.entries.iter().flat_map(|e| e.attachments.iter())
+    }
+
+    pub fn find_attachment(&self, attachment_id: &Uuid) -> Option<&AttachmentRef> {
+        self.attachment_refs().find(|a| a.id == *attachment_id)
+    }
+
+    /// Decrypts an attachment blob referenced by this vault.
+    pub fn open_attachment(&self, attachment_id: &Uuid, blob: &[u8]) -> Result<Vec<u8>> {
+        self.find_attachment(attachment_id)
+            .ok_or(RichieSafeError::AttachmentNotFound(*attachment_id))?;
+        attachment::open(&self.vault_key, attachment_id, blob)
+    }
+
+    /// Drops the reference; the caller deletes the stored blob.
+    pub fn remove_attachment(&mut self, entry_id: &Uuid, attachment_id: &Uuid) -> Result<()> {
+        let entry = self.get_entry(entry_id)?;
+        if !entry.attachments.iter().any(|a| a.id == *attachment_id) {
+            return Err(RichieSafeError::AttachmentNotFound(*attachment_id));
+        }
+        self.update_entry(entry_id, |e| e.attachments.retain(|a| a.id != *attachment_id))
+    }
+
+    /// Replaces all of an entry's attachments with one holding `data`; a file entry
+    /// takes the new MIME type and keeps no inline bytes. Returns the new reference
+    /// and blob, plus the ids of the dropped attachments whose stored blobs the caller deletes.
+    pub fn replace_attachments(
+        &mut self,
+        entry_id: &Uuid,
+        file_name: &str,
+        mime_type: Option<String>,
+        data: &[u8],
+    ) -> Result<(AttachmentRef, Vec<u8>, Vec<Uuid>)> {
+        let dropped: Vec<Uuid> = self.get_entry(entry_id)?.attachments.iter().map(|a| a.id).collect();
+        let (reference, blob) = self.add_attachment(entry_id, file_name, mime_type.clone(), data)?;
+        self.update_entry(entry_id, |e| {
+            e.attachments.retain(|a| a.id == reference.id);
+            if let EntryKind::File(file) = &mut e.kind {
+                file.mime_type = mime_type;
+                file.data.zeroize();
+                file.data.clear();
+            }
+        })?;
+        Ok((reference, blob, dropped))
+    }
+
+    /// Moves inline `FileData` content out of the vault body into attachments.
+    /// Returns the `(attachment id, blob)` pairs the caller must store before persisting
+    /// the re-sealed vault.
+    pub fn externalize_files(&mut self) -> Result<Vec<(Uuid, Vec<u8>)>> {
+        let suite = self.original_header.suite()?;
+        let mut blobs = Vec::new();
+        for entry in self.state.entries.iter_mut() {
+            let EntryKind::File(file) = &mut entry.kind else { continue };
+            if file.data.is_empty() {
+                continue;
+            }
+
+            let reference = AttachmentRef {
+                id: Uuid::new_v4(),
+                file_name: file.file_name.clone(),
+                mime_type: file.mime_type.clone(),
+                size: file.data.len() as u64,
+            };
+            blobs.push((reference.id, attachment::seal(suite, &self.vault_key, &reference.id, &file.data)?));
+            file.data.zeroize();
+            file.data.clear();
+            entry.attachments.push(reference);
+        }
+        Ok(blobs)
+    }
+}
+
+/// The single body encryption path: CBOR-encodes `state`, encrypts it with a fresh
+/// random nonce and binds the serialized header as AAD. v2 headers are written
+/// authenticated under `vault_key`.
+pub fn seal_vault(header: &VaultHeader, vault_key: &[u8; 32], state: &VaultState) -> Result<Vec<u8>> {
+    let mut body_bytes = serde_cbor::to_vec(state).map_err(|e| RichieSafeError::CborEncode(e.to_string()))?;
+
+    let mut header = header.clone();
+    header.authenticate(vault_key);
+
+    let suite = header.suite()?;
+    let body_nonce: [u8;24] = rng::generate_bytes(24).try_into().unwrap();
+    let header_bytes = header.to_bytes();
+
+    let ciphertext = suite.encrypt(
+        vault_key,
+        &body_nonce,
+        &body_bytes,
+        &header_bytes // AAD = Full header
+    );
+    body_bytes.zeroize();
+
+    Ok(format::assemble(&header, &body_nonce, &ciphertext?))
+}
+
+/// Inverse of [`seal_vault`] for an already unwrapped vault key.
+fn open_body(header: &VaultHeader, vault_key: &[u8; 32], body_nonce: &[u8; 24], body_ciphertext: &[u8]) -> Result<VaultState> {
+    let header_bytes = header.to_bytes();
+    let mut body_plaintext = header.suite()?.decrypt(
+        vault_key,
+        body_nonce,
+        body_ciphertext,
+        &header_bytes
+    ).map_err(|_| RichieSafeError::BodyDecryption)?;
+
+    let state = migration::decode_state(&body_plaintext);
+    body_plaintext.zeroize();
+    state
+}
+
 pub fn create_vault(
     vault_type: VaultType,
     pin: &str,
     recovery: &str,
     kdf_params_pin: kdf::KdfParams,
     kdf_params_rec: kdf::KdfParams
-) -> Result<Vec<u8>, String> {
-    // 1. Generate master key
-    let mut vault_key = rng::generate_bytes(32);
-    let mut vault_key_array: [u8;32] = vault_key.clone().try_into().unwrap();
+) -> Result<Vec<u8>> {
+    create_vault_with_suite(vault_type, pin, recovery, kdf_params_pin, kdf_params_rec, CipherSuite::DEFAULT)
+}
+
+/// [`create_vault`] with explicit algorithms. The suite is recorded in the header and
+/// kept by every later PIN change, rotation and rekey.
+pub fn create_vault_with_suite(
+    vault_type: VaultType,
+    pin: &str,
+    recovery: &str,
+    kdf_params_pin: kdf::KdfParams,
+    kdf_params_rec: kdf::KdfParams,
+    suite: CipherSuite,
+) -> Result<Vec<u8>> {
+    // 0. The PIN must satisfy the active strength policy
+    strength::policy().check(pin)?;
+
+    // 1. Generate master key (wiped on every return path)
+    let mut vault_key = Zeroizing::new([0u8; 32]);
+    rng::fill_bytes(vault_key.as_mut());
 
     // NORMALIZE INPUTS
     let pin = crate::util::normalize_input(pin);
     let recovery = crate::util::normalize_input(recovery);
 
-
     // 2. Prepare methods
-    let mut methods = Vec::new();
+    let methods = vec![
+        wrap_vault_key(suite, UnlockMethodType::Pin, &pin, &vault_key, kdf_params_pin)?,
+        wrap_vault_key(suite, UnlockMethodType::Recovery, &recovery, &vault_key, kdf_params_rec)?,
+    ];
 
-    // PIN Method
-    {
-        let salt = rng::generate_bytes(16);
-        let nonce = rng::generate_bytes(24);
-        let mut k_unlock = kdf::derive_key(pin.as_bytes(), &salt, kdf_params_pin)?;
-        
-        let wrapped_key = crypto_aead::encrypt(
-            &k_unlock, 
-            &nonce.clone().try_into().unwrap(), 
-            &vault_key, 
-            header::MAGIC // AAD = Magic for wrap
-        )?;
-        
-        if wrapped_key.len() != 48 { return Err("Wrap error".into()); }
-
-        methods.push(UnlockMethod {
-            method_id: UnlockMethodType::Pin,
-            kdf_params: kdf_params_pin,
-            method_salt: salt.try_into().unwrap(),
-            wrap_nonce: nonce.try_into().unwrap(),
-            wrapped_key: wrapped_key.try_into().unwrap(),
-        });
-        k_unlock.zeroize();
-    }
+    // 3. Create Header + empty Body and seal (the handle zeroizes its own copy on drop)
+    let handle = VaultHandle {
+        vault_key: *vault_key,
+        state: VaultState::new(),
+        original_header: VaultHeader::new(vault_type, methods).with_suite(suite),
+        unlocked_via: UnlockMethodType::Pin,
+    };
 
-    // Recovery Method
-    {
-        let salt = rng::generate_bytes(16);
-        let nonce = rng::generate_bytes(24);
-        let mut k_unlock = kdf::derive_key(recovery.as_bytes(), &salt, kdf_params_rec)?;
-        
-        let wrapped_key = crypto_aead::encrypt(
-            &k_unlock, 
-            &nonce.clone().try_into().unwrap(), 
-            &vault_key, 
-            header::MAGIC 
-        )?;
-
-         if wrapped_key.len() != 48 { return Err("Wrap error".into()); }
-
-        methods.push(UnlockMethod {
-            method_id: UnlockMethodType::Recovery,
-            kdf_params: kdf_params_rec,
-            method_salt: salt.try_into().unwrap(),
-            wrap_nonce: nonce.try_into().unwrap(),
-            wrapped_key: wrapped_key.try_into().unwrap(),
-        });
-        k_unlock.zeroize();
-    }
+    handle.seal()
+}
 
-    // 3. Create Header
-    let header = VaultHeader::new(vault_type, methods);
-    
-    // 4. Create Body
-    let state = VaultState::new();
-    let body_bytes = serde_cbor::to_vec(&state).map_err(|e| e.to_string())?;
-    
-    // 5. Encrypt Body
-    let body_nonce_vec = rng::generate_bytes(24);
-    let body_nonce: [u8;24] = body_nonce_vec.clone().try_into().unwrap();
-    let header_bytes = header.to_bytes();
-    
-    let ciphertext = crypto_aead::encrypt(
-        &vault_key_array,
-        &body_nonce,
-        &body_bytes,
-        &header_bytes // AAD = Full header
-    )?;
+/// How [`unlock_vault_with`] schedules the per-method derivations. Either way every
+/// candidate method is derived before a result is picked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnlockMode {
+    /// One derivation after another; peak memory is that of a single method.
+    #[default]
+    Sequential,
+    /// All derivations at once on scoped threads; peak memory is their sum.
+    /// Runs sequentially on wasm.
+    Parallel,
+}
 
-    // Zeroize vault key
-    vault_key.zeroize();
-    vault_key_array.zeroize();
+/// Unlocks with whichever method `secret` opens. The cost is the same whether the
+/// PIN, the recovery phrase or nothing matches.
+pub fn unlock_vault(blob: &[u8], secret: &str) -> Result<VaultHandle> {
+    unlock_vault_with(blob, secret, UnlockMode::Sequential)
+}
 
-    Ok(format::assemble(&header, &body_nonce, &ciphertext))
+pub fn unlock_vault_with(blob: &[u8], secret: &str, mode: UnlockMode) -> Result<VaultHandle> {
+    unlock_methods(blob, secret, None, mode)
 }
 
-pub fn unlock_vault(blob: &[u8], secret: &str) -> Result<VaultHandle, String> {
-    // 1. Split
+/// For when the UI already knows whether the user typed a PIN or the recovery phrase:
+/// only methods of `method_type` are derived.
+pub fn unlock_with_method(blob: &[u8], method_type: UnlockMethodType, secret: &str) -> Result<VaultHandle> {
+    unlock_methods(blob, secret, Some(method_type), UnlockMode::Sequential)
+}
+
+fn unlock_methods(
+    blob: &[u8],
+    secret: &str,
+    only: Option<UnlockMethodType>,
+    mode: UnlockMode,
+) -> Result<VaultHandle> {
+    // 1. Split (a damaged header commitment fails here, before any KDF work)
     let (header, body_nonce, body_ciphertext) = format::split(blob)?;
     
     // NORMALIZE SECRET
     let secret = crate::util::normalize_input(secret);
 
-    // 2. Try to unlock with available methods
-    let mut derived_key = [0u8; 32];
-    let mut decrypted_vault_key = Vec::new();
-
+    // 2. Refuse out-of-policy KDF costs up front so they surface as such, not as WrongSecret
+    let policy = kdf::policy();
     for method in &header.methods {
-        match kdf::derive_key(secret.as_bytes(), &method.method_salt, method.kdf_params) {
-            Ok(k) => derived_key = k,
-            Err(_) => continue,
-        };
-
-        match crypto_aead::decrypt(
-            &derived_key,
-            &method.wrap_nonce,
-            &method.wrapped_key,
-            header::MAGIC
-        ) {
-            Ok(vk) => {
-                decrypted_vault_key = vk;
-                break;
-            }
-            Err(_) => continue, // Tag mismatch = wrong password
-        }
+        policy.check(&method.kdf_params)?;
     }
+    let suite = header.suite()?;
 
-    if decrypted_vault_key.len() != 32 {
-        return Err("Authentication failed".into());
+    // 3. Derive for every candidate method, then take the first key that unwrapped
+    let candidates: Vec<&UnlockMethod> = header.methods.iter()
+        .filter(|m| only.is_none_or(|t| m.method_id == t))
+        .collect();
+    if candidates.is_empty() {
+        return Err(RichieSafeError::MissingUnlockMethod(only.unwrap_or(UnlockMethodType::Pin).name()));
     }
-    
-    let mut vault_key_array: [u8;32] = decrypted_vault_key.clone().try_into().unwrap();
-    decrypted_vault_key.zeroize();
-    
-    // 3. Decrypt Body
-    let header_bytes = header.to_bytes();
-    let body_plaintext = crypto_aead::decrypt(
-        &vault_key_array,
-        body_nonce,
-        body_ciphertext,
-        &header_bytes
-    )?;
-    
-    // 4. Decode State
-    let state: VaultState = serde_cbor::from_slice(&body_plaintext).map_err(|e| e.to_string())?;
+    let (unlocked_via, vault_key) = derive_all(suite, &candidates, &secret, mode)
+        .into_iter()
+        .zip(&candidates)
+        .fold(None, |found, (result, method)| found.or(result.ok().map(|key| (method.method_id, key)))) // Tag mismatch = wrong password
+        .ok_or(RichieSafeError::WrongSecret)?;
 
-    let handle = VaultHandle {
-        vault_key: vault_key_array,
+    // 4. The secret was right; a MAC mismatch now means the header was edited
+    header.verify_mac(&vault_key)?;
+
+    // 5. Decrypt + Decode Body
+    let state = open_body(&header, &vault_key, body_nonce, body_ciphertext)?;
+
+    Ok(VaultHandle {
+        vault_key: *vault_key,
         state,
         original_header: header,
-    };
-    
-    // Zeroize local copy of key
-    derived_key.zeroize();
-    vault_key_array.zeroize();
-    
-    Ok(handle)
+        unlocked_via,
+    })
 }
 
+/// Replaces the PIN method that `old_pin` opens. An unlocked session alone is not
+/// enough: `old_pin` is checked against the stored Pin methods (with their own salt
+/// and KDF params) before anything is rewrapped.
 pub fn change_pin(
     handle: &mut VaultHandle,
+    old_pin: &str,
     new_pin: &str,
     kdf_params: kdf::KdfParams,
-) -> Result<Vec<u8>, String> {
-    // 1. Identify existing recovery method to preserve
-    let recovery_method = handle.original_header.methods.iter()
-        .find(|m| m.method_id == UnlockMethodType::Recovery)
-        .ok_or("No recovery method found in original header")?
-        .clone();
-
-    // NORMALIZE NEW PIN
-    let new_pin = crate::util::normalize_input(new_pin);
-
-    // 2. Wrap existing vault_key with new PIN
-    // Generate new salt and nonce for wrapping
-    let salt = rng::generate_bytes(16);
-    let wrap_nonce = rng::generate_bytes(24);
-    
-    // Derived key from new PIN
-    let mut k_unlock = kdf::derive_key(new_pin.as_bytes(), &salt, kdf_params)?;
-    
-    // Wrap the *existing* vault_key
-    let wrapped_key_vec = crypto_aead::encrypt(
+) -> Result<Vec<u8>> {
+    // 1. A recovery method must survive the PIN change
+    if !handle.original_header.methods.iter().any(|m| m.method_id == UnlockMethodType::Recovery) {
+        return Err(RichieSafeError::MissingUnlockMethod("recovery"));
+    }
+    strength::policy().check(new_pin)?;
+
+    // NORMALIZE PINS
+    let old_pin = Zeroizing::new(crate::util::normalize_input(old_pin));
+    let new_pin = Zeroizing::new(crate::util::normalize_input(new_pin));
+
+    // 2. Verify the current PIN and find which Pin method it belongs to
+    let pin_methods: Vec<usize> = handle.original_header.methods.iter()
+        .enumerate()
+        .filter(|(_, m)| m.method_id == UnlockMethodType::Pin)
+        .map(|(i, _)| i)
+        .collect();
+    if pin_methods.is_empty() {
+        return Err(RichieSafeError::MissingUnlockMethod("pin"));
+    }
+    let suite = handle.original_header.suite()?;
+    let pos = pin_methods.into_iter()
+        .find(|&i| {
+            unwrap_method(suite, &handle.original_header.methods[i], &old_pin)
+                .is_ok_and(|k| *k == handle.vault_key)
+        })
+        .ok_or(RichieSafeError::PinVerificationFailed)?;
+
+    // 3. Wrap the *existing* vault_key with new PIN, replacing only the verified method;
+    // every other method (extra PINs, recovery) is kept as-is
+    let pin_method = wrap_vault_key(suite, UnlockMethodType::Pin, &new_pin, &handle.vault_key, kdf_params)?;
+    let mut methods = handle.original_header.methods.clone();
+    methods[pos] = pin_method;
+
+    // 4. New Header + Re-Encrypt Body (because Header/AAD changed) and update handle header
+    let header = rebuild_header(handle, methods);
+    handle.reencrypt(header)
+}
+
+/// Sets a new PIN without the old one, for a vault opened by its recovery phrase
+/// (e.g. after the PIN was forgotten or throttling required recovery). Every Pin
+/// method is replaced by a single new one.
+pub fn reset_pin(handle: &mut VaultHandle, new_pin: &str, kdf_params: kdf::KdfParams) -> Result<Vec<u8>> {
+    if handle.unlocked_via != UnlockMethodType::Recovery {
+        return Err(RichieSafeError::PinVerificationFailed);
+    }
+    strength::policy().check(new_pin)?;
+
+    let new_pin = Zeroizing::new(crate::util::normalize_input(new_pin));
+    let suite = handle.original_header.suite()?;
+    let pin_method = wrap_vault_key(suite, UnlockMethodType::Pin, &new_pin, &handle.vault_key, kdf_params)?;
+
+    // The new PIN takes the slot of the first old one (or goes first)
+    let old_methods = &handle.original_header.methods;
+    let slot = old_methods.iter().position(|m| m.method_id == UnlockMethodType::Pin).unwrap_or(0);
+    let mut methods: Vec<UnlockMethod> = old_methods.iter()
+        .filter(|m| m.method_id != UnlockMethodType::Pin)
+        .cloned()
+        .collect();
+    methods.insert(slot.min(methods.len()), pin_method);
+
+    let header = rebuild_header(handle, methods);
+    handle.reencrypt(header)
+}
+
+/// Drops every Pin method, leaving the vault openable only by its recovery phrase
+/// until `reset_pin`. Returns the resealed blob.
+pub fn remove_pin_methods(handle: &mut VaultHandle) -> Result<Vec<u8>> {
+    let methods: Vec<UnlockMethod> = handle.original_header.methods.iter()
+        .filter(|m| m.method_id != UnlockMethodType::Pin)
+        .cloned()
+        .collect();
+    if methods.is_empty() {
+        return Err(RichieSafeError::MissingUnlockMethod("recovery"));
+    }
+    let header = rebuild_header(handle, methods);
+    handle.reencrypt(header)
+}
+
+/// Replaces a possibly exposed recovery phrase. The vault key is rewrapped under
+/// `new_recovery`; every previous Recovery method is dropped and PIN methods are kept.
+pub fn rotate_recovery(
+    handle: &mut VaultHandle,
+    new_recovery: &str,
+    kdf_params: kdf::KdfParams,
+) -> Result<Vec<u8>> {
+    // 1. The PIN must survive the rotation
+    if !handle.original_header.methods.iter().any(|m| m.method_id == UnlockMethodType::Pin) {
+        return Err(RichieSafeError::MissingUnlockMethod("pin"));
+    }
+
+    // NORMALIZE NEW RECOVERY
+    let new_recovery = crate::util::normalize_input(new_recovery);
+
+    // 2. Wrap the *existing* vault_key with the new phrase
+    let suite = handle.original_header.suite()?;
+    let recovery_method = wrap_vault_key(suite, UnlockMethodType::Recovery, &new_recovery, &handle.vault_key, kdf_params)?;
+
+    // Take the slot of the first old recovery method, drop any others
+    let old_methods = &handle.original_header.methods;
+    let slot = old_methods.iter()
+        .position(|m| m.method_id == UnlockMethodType::Recovery)
+        .unwrap_or(old_methods.len());
+    let mut methods: Vec<UnlockMethod> = old_methods[..slot].to_vec();
+    methods.push(recovery_method);
+    methods.extend(old_methods[slot..].iter()
+        .filter(|m| m.method_id != UnlockMethodType::Recovery)
+        .cloned());
+
+    // 3. New Header + Re-Encrypt Body
+    let header = rebuild_header(handle, methods);
+    handle.reencrypt(header)
+}
+
+/// Output of [`rekey_vault`]: the new vault blob and every attachment re-sealed under the new key.
+pub struct RekeyOutput {
+    pub blob: Vec<u8>,
+    pub attachments: Vec<(Uuid, Vec<u8>)>,
+}
+
+/// Replaces the vault key itself, for when it may have leaked (changing a PIN only rewraps it).
+///
+/// `secrets` holds one secret per unlock method, in header order; each is checked against
+/// its method before anything changes. `attachments` must contain the stored blob of every
+/// attachment referenced by the vault (unreferenced ones are ignored).
+/// The handle switches to the new key only once everything has been re-encrypted.
+pub fn rekey_vault(
+    handle: &mut VaultHandle,
+    secrets: &[&str],
+    attachments: &[(Uuid, Vec<u8>)],
+) -> Result<RekeyOutput> {
+    let old_methods = &handle.original_header.methods;
+    if secrets.len() != old_methods.len() {
+        return Err(RichieSafeError::MissingUnlockMethod("a secret for every unlock method"));
+    }
+
+    // 1. Verify every secret against its current method
+    let suite = handle.original_header.suite()?;
+    let secrets: Vec<Zeroizing<String>> = secrets.iter()
+        .map(|s| Zeroizing::new(crate::util::normalize_input(s)))
+        .collect();
+    for (method, secret) in old_methods.iter().zip(&secrets) {
+        unwrap_method(suite, method, secret)?;
+    }
+
+    // 2. Generate the new master key
+    let mut new_key = Zeroizing::new([0u8; 32]);
+    rng::fill_bytes(new_key.as_mut());
+
+    // 3. Re-encrypt all attachments
+    let mut resealed = Vec::new();
+    for reference in handle.attachment_refs() {
+        let (_, blob) = attachments.iter()
+            .find(|(id, _)| *id == reference.id)
+            .ok_or(RichieSafeError::AttachmentNotFound(reference.id))?;
+        let plaintext = Zeroizing::new(attachment::open(&handle.vault_key, &reference.id, blob)?);
+        resealed.push((reference.id, attachment::seal(suite, &new_key, &reference.id, &plaintext)?));
+    }
+
+    // 4. Rewrap every method (same type and KDF params, fresh salt/nonce)
+    let methods = old_methods.iter().zip(&secrets)
+        .map(|(method, secret)| wrap_vault_key(suite, method.method_id, secret, &new_key, method.kdf_params))
+        .collect::<Result<Vec<_>>>()?;
+
+    // 5. Seal the body under the new key, then switch the handle over
+    let header = rebuild_header(handle, methods);
+    let blob = seal_vault(&header, &new_key, &handle.state)?;
+
+    handle.vault_key.zeroize();
+    handle.vault_key = *new_key;
+    handle.original_header = header;
+
+    Ok(RekeyOutput { blob, attachments: resealed })
+}
+
+/// Runs [`unwrap_method`] for every method without stopping at the first match.
+/// Returns one result per method, in order.
+fn derive_all(
+    suite: CipherSuite,
+    methods: &[&UnlockMethod],
+    secret: &str,
+    mode: UnlockMode,
+) -> Vec<Result<Zeroizing<[u8; 32]>>> {
+    if mode == UnlockMode::Parallel && methods.len() > 1 && cfg!(not(target_arch = "wasm32")) {
+        return std::thread::scope(|scope| {
+            let workers: Vec<_> = methods.iter()
+                .map(|method| scope.spawn(move || unwrap_method(suite, method, secret)))
+                .collect();
+            workers.into_iter()
+                .map(|w| w.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect()
+        });
+    }
+    methods.iter().map(|method| unwrap_method(suite, method, secret)).collect()
+}
+
+/// Unwraps the vault key from one method with an already normalized `secret`.
+fn unwrap_method(suite: CipherSuite, method: &UnlockMethod, secret: &str) -> Result<Zeroizing<[u8; 32]>> {
+    let k_unlock = Zeroizing::new(suite.derive_key(secret.as_bytes(), &method.method_salt, method.kdf_params)?);
+
+    let vault_key = Zeroizing::new(suite.decrypt(
         &k_unlock,
-        &wrap_nonce.clone().try_into().unwrap(), // Need try_into for array
-        &handle.vault_key, 
+        &method.wrap_nonce,
+        &method.wrapped_key,
         header::MAGIC
-    )?;
+    ).map_err(|_| RichieSafeError::WrongSecret)?);
 
-    if wrapped_key_vec.len() != 48 {
-        return Err("Wrap error".into());
+    let mut out = Zeroizing::new([0u8; 32]);
+    if vault_key.len() != out.len() {
+        return Err(RichieSafeError::WrongSecret);
     }
+    out.copy_from_slice(&vault_key);
+    Ok(out)
+}
 
-    let mut methods = Vec::new();
-    methods.push(UnlockMethod {
-        method_id: UnlockMethodType::Pin,
-        kdf_params,
-        method_salt: salt.try_into().unwrap(),
-        wrap_nonce: wrap_nonce.try_into().unwrap(),
-        wrapped_key: wrapped_key_vec.try_into().unwrap(),
-    });
-    methods.push(recovery_method);
-    
+/// Derives an unlock key from an already normalized `secret` and wraps `vault_key` with it.
+fn wrap_vault_key(
+    suite: CipherSuite,
+    method_id: UnlockMethodType,
+    secret: &str,
+    vault_key: &[u8; 32],
+    kdf_params: kdf::KdfParams,
+) -> Result<UnlockMethod> {
+    // Fresh salt and nonce for every wrap
+    let salt = rng::generate_bytes(16);
+    let wrap_nonce: [u8; 24] = rng::generate_bytes(24).try_into().unwrap();
+
+    let mut k_unlock = suite.derive_key(secret.as_bytes(), &salt, kdf_params)?;
+    let wrapped_key = suite.encrypt(
+        &k_unlock,
+        &wrap_nonce,
+        vault_key,
+        header::MAGIC // AAD = Magic for wrap
+    );
     k_unlock.zeroize();
 
-    // 3. New Header using original vault type
-    let header = VaultHeader::new(handle.original_header.fixed.vault_type, methods);
-    
-    // UPDATE HANDLE HEADER
-    handle.original_header = header.clone();
+    let wrapped_key: [u8; 48] = wrapped_key?.try_into()
+        .map_err(|_| RichieSafeError::Encryption)?;
 
-    // 4. Re-Encrypt Body (because Header/AAD changed)
-    // Serialize state
-    let state = &handle.state;
-    let body_bytes = serde_cbor::to_vec(state).map_err(|e| e.to_string())?;
+    Ok(UnlockMethod {
+        method_id,
+        kdf_params,
+        method_salt: salt.try_into().unwrap(),
+        wrap_nonce,
+        wrapped_key,
+    })
+}
 
-    // New body nonce
-    let body_nonce_vec = rng::generate_bytes(24);
-    let body_nonce: [u8;24] = body_nonce_vec.clone().try_into().unwrap();
-    
-    let header_bytes = header.to_bytes();
-    
-    let ciphertext = crypto_aead::encrypt(
-        &handle.vault_key,
-        &body_nonce,
-        &body_bytes,
-        &header_bytes // New AAD
-    )?;
+/// Header for a new method list, keeping the original vault type, algorithms and
+/// `header_salt` (which identifies the vault to its attempt record).
+/// v1 vaults stay v1 as long as they still fit; use `upgrade_vault` to move them to v2.
+fn rebuild_header(handle: &VaultHandle, methods: Vec<UnlockMethod>) -> VaultHeader {
+    let original = &handle.original_header.fixed;
+    let mut header = VaultHeader::new(original.vault_type, methods);
+    header.fixed.kdf_id = original.kdf_id;
+    header.fixed.aead_id = original.aead_id;
+    header.fixed.header_salt = original.header_salt;
+    if handle.original_header.fixed.version == header::VERSION_V1 && header.methods.len() == 2 {
+        header.fixed.version = header::VERSION_V1;
+    }
+    header
+}
 
-    Ok(format::assemble(&header, &body_nonce, &ciphertext))
+/// Rewrites a vault read from a v1 (`RSAFEV1\0`, fixed 234-byte) header using the v2 layout.
+/// The vault key and unlock methods are unchanged; only the header encoding and the body AAD change.
+pub fn upgrade_vault(handle: &mut VaultHandle) -> Result<Vec<u8>> {
+    let mut header = handle.original_header.clone();
+    header.fixed.version = header::VERSION_V2;
+    handle.reencrypt(header)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::aead::AeadAlgorithm;
     use crate::crypto::kdf::KdfParams;
+    use crate::models::entry::{EntryKind, LoginData};
 
     #[test]
     fn test_vault_flow() {
         let pin = "123456";
         let recovery = "word word word";
-        // Use very low cost for fast test
-        let params = KdfParams { m_cost: 1024, t_cost: 1, p_cost: 1 }; 
+        // Use the lowest cost the policy allows for a fast test
+        let params = KdfParams::FLOOR;
         
         // 1. Create
         let blob_res = create_vault(VaultType::Real, pin, recovery, params, params);
@@ -277,10 +680,456 @@ mod tests {
         assert!(handle_rec_res.is_ok(), "Failed to unlock with Recovery");
         
         // 4. Fail with wrong secret
-        assert!(unlock_vault(&blob, "wrong123").is_err());
+        assert!(matches!(unlock_vault(&blob, "wrong123"), Err(RichieSafeError::WrongSecret)));
         
         // 5. Verify Header Type
         let (header, _, _) = format::split(&blob).unwrap();
         assert_eq!(header.fixed.vault_type, VaultType::Real);
     }
+
+    fn low_params() -> KdfParams {
+        KdfParams::FLOOR
+    }
+
+    #[test]
+    fn test_every_header_byte_is_covered() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let header_len = header::VaultHeader::peek_len(&blob).unwrap();
+        assert_eq!(VaultHeader::parse(&blob[..header_len]).unwrap().fixed.flags & header::FLAG_HEADER_AUTH, header::FLAG_HEADER_AUTH);
+
+        for i in 0..header_len {
+            let mut tampered = blob.clone();
+            tampered[i] ^= 0x01;
+            let err = unlock_vault(&tampered, "1234").err().unwrap_or_else(|| panic!("byte {i} not covered"));
+            match i {
+                0..=7 => assert_eq!(err, RichieSafeError::CorruptedHeader("invalid magic"), "byte {i}"),
+                8 => assert_eq!(err, RichieSafeError::UnsupportedVersion(3), "byte {i}"),
+                9 => assert_eq!(err, RichieSafeError::UnsupportedVersion(0x0102), "byte {i}"),
+                // The length field moves the header/body boundary
+                32..=35 => assert!(
+                    matches!(err, RichieSafeError::CorruptedHeader(_) | RichieSafeError::TruncatedBlob | RichieSafeError::HeaderTampered),
+                    "byte {i}: {err:?}"
+                ),
+                _ => assert_eq!(err, RichieSafeError::HeaderTampered, "byte {i}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_recomputed_commitment_is_caught_by_mac() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let (mut header, nonce, ciphertext) = format::split(&blob).unwrap();
+
+        // Someone without the vault key edits the recovery method and fixes up the hash
+        header.methods[1].kdf_params.t_cost += 1;
+        let tampered = format::assemble(&header, nonce, ciphertext);
+        assert!(format::split(&tampered).is_ok());
+
+        assert_eq!(unlock_vault(&tampered, "1234").err(), Some(RichieSafeError::HeaderTampered));
+        assert_eq!(unlock_vault(&tampered, "0000").err(), Some(RichieSafeError::WrongSecret));
+    }
+
+    #[test]
+    fn test_stripped_header_auth_is_rejected() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let handle = unlock_vault(&blob, "1234").unwrap();
+
+        // Downgrade attempt: clear the flag, drop the 64-byte trailer, re-encrypt the body to match
+        let mut stripped = handle.original_header.clone();
+        stripped.fixed.flags = 0;
+        stripped.mac = None;
+        let mut body_bytes = serde_cbor::to_vec(&handle.state).unwrap();
+        let nonce = [9u8; 24];
+        let ciphertext = crate::crypto::aead::encrypt(&handle.vault_key, &nonce, &body_bytes, &stripped.to_bytes()).unwrap();
+        body_bytes.zeroize();
+        let stripped_blob = format::assemble(&stripped, &nonce, &ciphertext);
+        assert_eq!(stripped_blob.len(), blob.len() - header::HEADER_AUTH_SIZE);
+
+        assert_eq!(unlock_vault(&stripped_blob, "1234").err(), Some(RichieSafeError::HeaderTampered));
+        assert_eq!(unlock_vault(&stripped_blob, "rec").err(), Some(RichieSafeError::HeaderTampered));
+    }
+
+    #[test]
+    fn test_unlock_derives_every_method() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let (header, _, _) = format::split(&blob).unwrap();
+        let methods: Vec<&UnlockMethod> = header.methods.iter().collect();
+
+        for mode in [UnlockMode::Sequential, UnlockMode::Parallel] {
+            let pin: Vec<bool> = derive_all(CipherSuite::DEFAULT, &methods, "1234", mode).iter().map(|r| r.is_ok()).collect();
+            let rec: Vec<bool> = derive_all(CipherSuite::DEFAULT, &methods, "rec", mode).iter().map(|r| r.is_ok()).collect();
+            assert_eq!((pin, rec), (vec![true, false], vec![false, true]));
+
+            assert!(unlock_vault_with(&blob, "1234", mode).is_ok());
+            assert!(unlock_vault_with(&blob, "rec", mode).is_ok());
+            assert_eq!(unlock_vault_with(&blob, "nope", mode).err(), Some(RichieSafeError::WrongSecret));
+        }
+    }
+
+    #[test]
+    fn test_unlocked_via() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        for mode in [UnlockMode::Sequential, UnlockMode::Parallel] {
+            assert_eq!(unlock_vault_with(&blob, "1234", mode).unwrap().unlocked_via(), UnlockMethodType::Pin);
+            assert_eq!(unlock_vault_with(&blob, "rec", mode).unwrap().unlocked_via(), UnlockMethodType::Recovery);
+        }
+        let handle = unlock_with_method(&blob, UnlockMethodType::Recovery, "rec").unwrap();
+        assert_eq!(handle.unlocked_via(), UnlockMethodType::Recovery);
+    }
+
+    #[test]
+    fn test_reset_pin_needs_recovery_unlock() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let mut by_pin = unlock_vault(&blob, "1234").unwrap();
+        assert_eq!(reset_pin(&mut by_pin, "5678", low_params()).err(), Some(RichieSafeError::PinVerificationFailed));
+
+        let mut by_recovery = unlock_vault(&blob, "rec").unwrap();
+        let without_pin = remove_pin_methods(&mut by_recovery).unwrap();
+        assert_eq!(unlock_vault(&without_pin, "1234").err(), Some(RichieSafeError::WrongSecret));
+
+        let reset = reset_pin(&mut by_recovery, "5678", low_params()).unwrap();
+        let reopened = unlock_vault(&reset, "5678").unwrap();
+        assert_eq!(reopened.original_header.methods[0].method_id, UnlockMethodType::Pin);
+        assert_eq!(reopened.original_header.fixed.header_salt, by_pin.original_header.fixed.header_salt);
+        assert!(unlock_vault(&reset, "rec").is_ok());
+    }
+
+    #[test]
+    fn test_unlock_with_method() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        assert!(unlock_with_method(&blob, UnlockMethodType::Pin, "1234").is_ok());
+        assert!(unlock_with_method(&blob, UnlockMethodType::Recovery, "rec").is_ok());
+        assert_eq!(unlock_with_method(&blob, UnlockMethodType::Pin, "rec").err(), Some(RichieSafeError::WrongSecret));
+        assert_eq!(unlock_with_method(&blob, UnlockMethodType::Recovery, "1234").err(), Some(RichieSafeError::WrongSecret));
+
+        let handle = unlock_vault(&blob, "1234").unwrap();
+        let mut pin_only = handle.original_header.clone();
+        pin_only.methods.retain(|m| m.method_id == UnlockMethodType::Pin);
+        pin_only.fixed.unlock_methods_count = 1;
+        let pin_only = seal_vault(&pin_only, &handle.vault_key, &handle.state).unwrap();
+        assert_eq!(
+            unlock_with_method(&pin_only, UnlockMethodType::Recovery, "rec").err(),
+            Some(RichieSafeError::MissingUnlockMethod("recovery"))
+        );
+    }
+
+    #[test]
+    fn test_gcm_siv_vault_keeps_its_suite() {
+        let suite = CipherSuite { aead: AeadAlgorithm::Aes256GcmSiv, ..CipherSuite::DEFAULT };
+        let blob = create_vault_with_suite(VaultType::Real, "1234", "rec", low_params(), low_params(), suite).unwrap();
+
+        let mut handle = unlock_vault(&blob, "1234").unwrap();
+        assert_eq!(handle.original_header.suite(), Ok(suite));
+        let changed = change_pin(&mut handle, "1234", "5678", low_params()).unwrap();
+        let rotated = rotate_recovery(&mut unlock_vault(&changed, "5678").unwrap(), "new", low_params()).unwrap();
+
+        let mut reopened = unlock_vault(&rotated, "new").unwrap();
+        assert_eq!(reopened.original_header.fixed.aead_id, AeadAlgorithm::Aes256GcmSiv.id());
+        assert!(unlock_vault(&rotated, "5678").is_ok());
+
+        // Attachments follow the vault's AEAD
+        let entry_id = reopened.add_entry(sample_entry("Passport"));
+        let (reference, att_blob) = reopened.add_attachment(&entry_id, "scan.jpg", None, b"scan").unwrap();
+        assert_eq!(att_blob[8], AeadAlgorithm::Aes256GcmSiv.id());
+        assert_eq!(reopened.open_attachment(&reference.id, &att_blob).unwrap(), b"scan");
+    }
+
+    #[test]
+    fn test_unknown_algorithm_ids_are_rejected() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let (header, nonce, ciphertext) = format::split(&blob).unwrap();
+
+        let mut unknown_aead = header.clone();
+        unknown_aead.fixed.aead_id = 0x7F;
+        let unknown_aead = format::assemble(&unknown_aead, nonce, ciphertext);
+        assert_eq!(unlock_vault(&unknown_aead, "1234").err(), Some(RichieSafeError::UnsupportedAead(0x7F)));
+
+        let mut unknown_kdf = header;
+        unknown_kdf.fixed.kdf_id = 0x00;
+        let unknown_kdf = format::assemble(&unknown_kdf, nonce, ciphertext);
+        assert_eq!(unlock_vault(&unknown_kdf, "1234").err(), Some(RichieSafeError::UnsupportedKdf(0)));
+    }
+
+    #[test]
+    fn test_unlock_rejects_out_of_policy_header() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let handle = unlock_vault(&blob, "1234").unwrap();
+
+        // A synced blob asking for 4 GiB on the recovery method
+        let mut hostile = handle.original_header.clone();
+        hostile.methods[1].kdf_params.m_cost = 4 * 1024 * 1024;
+        let hostile = seal_vault(&hostile, &handle.vault_key, &handle.state).unwrap();
+        for secret in ["1234", "rec", "wrong"] {
+            assert!(matches!(unlock_vault(&hostile, secret), Err(RichieSafeError::KdfOutOfPolicy(_))));
+        }
+
+        // Creation is held to the same policy
+        let weak = KdfParams { m_cost: 1024, t_cost: 1, p_cost: 1 };
+        assert!(matches!(
+            create_vault(VaultType::Real, "1234", "rec", weak, weak),
+            Err(RichieSafeError::KdfOutOfPolicy(_))
+        ));
+    }
+
+    #[test]
+    fn test_upgrade_v1_to_v2() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let handle = unlock_vault(&blob, "1234").unwrap();
+        assert_eq!(handle.original_header.fixed.version, header::VERSION_V2);
+
+        // Re-seal the vault with a v1 header to simulate a legacy blob
+        let mut legacy = handle.original_header.clone();
+        legacy.fixed.version = header::VERSION_V1;
+        let v1_blob = seal_vault(&legacy, &handle.vault_key, &handle.state).unwrap();
+        assert_eq!(&v1_blob[8..10], &1u16.to_le_bytes());
+
+        let mut v1_handle = unlock_vault(&v1_blob, "rec").unwrap();
+        assert_eq!(v1_handle.original_header.fixed.version, header::VERSION_V1);
+
+        // PIN change keeps the v1 layout
+        let changed = change_pin(&mut v1_handle, "1234", "5678", low_params()).unwrap();
+        assert_eq!(format::split(&changed).unwrap().0.fixed.version, header::VERSION_V1);
+
+        let upgraded = upgrade_vault(&mut v1_handle).unwrap();
+        let (header, _, _) = format::split(&upgraded).unwrap();
+        assert_eq!(header.fixed.version, header::VERSION_V2);
+        assert!(unlock_vault(&upgraded, "5678").is_ok());
+        assert!(unlock_vault(&upgraded, "rec").is_ok());
+    }
+
+    #[test]
+    fn test_change_pin_keeps_extra_methods() {
+        let blob = create_vault(VaultType::Real, "1111", "rec", low_params(), low_params()).unwrap();
+        let mut handle = unlock_vault(&blob, "1111").unwrap();
+
+        // Add a second PIN
+        let extra = wrap_vault_key(CipherSuite::DEFAULT, UnlockMethodType::Pin, "3333", &handle.vault_key, low_params()).unwrap();
+        handle.original_header.methods.push(extra);
+
+        // Only the PIN that was verified gets replaced
+        let new_blob = change_pin(&mut handle, "3333", "2222", low_params()).unwrap();
+        let (header, _, _) = format::split(&new_blob).unwrap();
+        assert_eq!(header.methods.len(), 3);
+        assert!(unlock_vault(&new_blob, "2222").is_ok());
+        assert!(unlock_vault(&new_blob, "1111").is_ok());
+        assert!(unlock_vault(&new_blob, "rec").is_ok());
+        assert!(unlock_vault(&new_blob, "3333").is_err());
+    }
+
+    #[test]
+    fn test_change_pin_requires_current_pin() {
+        let blob = create_vault(VaultType::Real, "1111", "rec", low_params(), low_params()).unwrap();
+        let mut handle = unlock_vault(&blob, "1111").unwrap();
+        let header_before = handle.original_header.to_bytes();
+
+        // Wrong PIN, and the recovery phrase is not a PIN
+        assert_eq!(change_pin(&mut handle, "9999", "2222", low_params()).err(), Some(RichieSafeError::PinVerificationFailed));
+        assert_eq!(change_pin(&mut handle, "rec", "2222", low_params()).err(), Some(RichieSafeError::PinVerificationFailed));
+        assert_eq!(handle.original_header.to_bytes(), header_before);
+
+        let new_blob = change_pin(&mut handle, " 1111 ", "2222", low_params()).unwrap();
+        assert!(unlock_vault(&new_blob, "2222").is_ok());
+        assert!(unlock_vault(&new_blob, "1111").is_err());
+    }
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let handle = VaultHandle::open(&blob, "1234").unwrap();
+
+        let first = handle.seal().unwrap();
+        let second = handle.seal().unwrap();
+        // Same header, fresh body nonce each time
+        let header_len = handle.original_header.encoded_len();
+        assert_eq!(first[..header_len], second[..header_len]);
+        assert_ne!(first[header_len..header_len + 24], second[header_len..header_len + 24]);
+
+        let reopened = VaultHandle::open(&first, "rec").unwrap();
+        assert_eq!(reopened.state.vault_uuid, handle.state.vault_uuid);
+
+        // Edited header bytes fail the commitment before the body is tried
+        let mut spliced = blob[..header_len].to_vec();
+        spliced[16] ^= 0xFF; // header_salt
+        spliced.extend_from_slice(&first[header_len..]);
+        assert_eq!(unlock_vault(&spliced, "1234").err(), Some(RichieSafeError::HeaderTampered));
+
+        // Body is bound to the header: even a validly authenticated other header breaks it
+        let mut other = handle.original_header.clone();
+        other.fixed.header_salt[0] ^= 0xFF;
+        other.authenticate(&handle.vault_key);
+        let mut spliced = other.to_bytes();
+        spliced.extend_from_slice(&first[header_len..]);
+        assert_eq!(unlock_vault(&spliced, "1234").err(), Some(RichieSafeError::BodyDecryption));
+    }
+
+    fn sample_entry(title: &str) -> VaultEntry {
+        VaultEntry::new(title, EntryKind::Login(LoginData {
+            username: "user".to_string(),
+            password: Some("hunter2".to_string()),
+            url: None,
+        }))
+    }
+
+    #[test]
+    fn test_entry_crud() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let mut handle = unlock_vault(&blob, "1234").unwrap();
+
+        let id = handle.add_entry(sample_entry("Mail"));
+        let other = handle.add_entry(sample_entry("Bank"));
+        assert_eq!(handle.get_entry(&id).unwrap().title, "Mail");
+
+        let created_at = handle.get_entry(&id).unwrap().created_at;
+        handle.update_entry(&id, |e| {
+            e.id = Uuid::new_v4();
+            e.title = "Mail (work)".to_string();
+            e.notes = Some("correct horse".to_string());
+        }).unwrap();
+
+        let updated = handle.get_entry(&id).unwrap();
+        assert_eq!(updated.title, "Mail (work)");
+        assert_eq!(updated.notes.as_deref(), Some("correct horse"));
+        assert_eq!(updated.created_at, created_at);
+        assert!(updated.updated_at >= created_at);
+
+        handle.delete_entry(&other).unwrap();
+        assert!(handle.find_by_id(&other).is_none());
+        assert_eq!(handle.delete_entry(&other), Err(RichieSafeError::EntryNotFound(other)));
+        assert!(handle.update_entry(&other, |_| {}).is_err());
+
+        let reopened = VaultHandle::open(&handle.seal().unwrap(), "1234").unwrap();
+        assert_eq!(reopened.state.entries.len(), 1);
+        assert_eq!(reopened.get_entry(&id).unwrap().title, "Mail (work)");
+    }
+
+    #[test]
+    fn test_entry_otp_codes() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let mut handle = unlock_vault(&blob, "1234").unwrap();
+        let totp = handle.add_entry(sample_entry("Mail"));
+        let hotp = handle.add_entry(sample_entry("VPN"));
+        assert_eq!(handle.entry_otp_code(&totp, 59), Err(RichieSafeError::OtpNotConfigured(totp)));
+
+        // RFC 4226 / 6238 seed "12345678901234567890"
+        let seed = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        handle.update_entry(&totp, |e| e.otp = OtpSecret::from_uri(&format!("otpauth://totp/Mail?secret={seed}&digits=8")).ok()).unwrap();
+        handle.update_entry(&hotp, |e| e.otp = OtpSecret::from_uri(&format!("otpauth://hotp/VPN?secret={seed}&counter=0")).ok()).unwrap();
+
+        let mut handle = VaultHandle::open(&handle.seal().unwrap(), "1234").unwrap();
+        assert_eq!(handle.entry_otp_code(&totp, 59).unwrap().code, "94287082");
+        assert_eq!(handle.next_hotp_code(&hotp).unwrap().code, "755224");
+        assert_eq!(handle.next_hotp_code(&hotp).unwrap().code, "287082");
+        assert_eq!(handle.entry_otp_code(&hotp, 0).unwrap().code, "359152");
+        assert!(matches!(handle.next_hotp_code(&totp), Err(RichieSafeError::InvalidOtp(_))));
+    }
+
+    #[test]
+    fn test_attachments_live_outside_the_body() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let mut handle = unlock_vault(&blob, "1234").unwrap();
+        let entry_id = handle.add_entry(sample_entry("Passport"));
+
+        let photo = vec![0xABu8; 64 * 1024];
+        let (reference, att_blob) = handle.add_attachment(&entry_id, "scan.jpg", Some("image/jpeg".into()), &photo).unwrap();
+        assert_eq!(reference.size, photo.len() as u64);
+
+        // The body only carries the reference, not the data
+        let sealed = handle.seal().unwrap();
+        assert!(sealed.len() < 4096);
+
+        let reopened = VaultHandle::open(&sealed, "rec").unwrap();
+        assert_eq!(reopened.get_entry(&entry_id).unwrap().attachments.len(), 1);
+        assert_eq!(reopened.open_attachment(&reference.id, &att_blob).unwrap(), photo);
+
+        handle.remove_attachment(&entry_id, &reference.id).unwrap();
+        assert_eq!(handle.open_attachment(&reference.id, &att_blob), Err(RichieSafeError::AttachmentNotFound(reference.id)));
+    }
+
+    #[test]
+    fn test_externalize_inline_files() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let mut handle = unlock_vault(&blob, "1234").unwrap();
+        let id = handle.add_entry(VaultEntry::new("Photo", EntryKind::File(crate::models::entry::FileData {
+            file_name: "a.png".into(),
+            mime_type: Some("image/png".into()),
+            data: vec![1, 2, 3],
+        })));
+
+        let blobs = handle.externalize_files().unwrap();
+        assert_eq!(blobs.len(), 1);
+        let entry = handle.get_entry(&id).unwrap();
+        assert!(matches!(&entry.kind, EntryKind::File(f) if f.data.is_empty()));
+        assert_eq!(handle.open_attachment(&blobs[0].0, &blobs[0].1).unwrap(), vec![1, 2, 3]);
+        assert!(handle.externalize_files().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_replace_attachments_drops_the_old_one() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let mut handle = unlock_vault(&blob, "1234").unwrap();
+        let id = handle.add_entry(VaultEntry::new("Photo", EntryKind::File(crate::models::entry::FileData {
+            file_name: "a.png".into(),
+            mime_type: Some("image/png".into()),
+            data: Vec::new(),
+        })));
+        let (old, _) = handle.add_attachment(&id, "a.png", Some("image/png".into()), &[1, 2, 3]).unwrap();
+
+        let (new, new_blob, dropped) = handle.replace_attachments(&id, "a.jpg", Some("image/jpeg".into()), &[4, 5]).unwrap();
+        assert_eq!(dropped, vec![old.id]);
+        let entry = handle.get_entry(&id).unwrap();
+        assert_eq!(entry.attachments.len(), 1);
+        assert!(matches!(&entry.kind, EntryKind::File(f) if f.data.is_empty() && f.mime_type.as_deref() == Some("image/jpeg")));
+        assert_eq!(handle.open_attachment(&new.id, &new_blob).unwrap(), vec![4, 5]);
+    }
+
+    #[test]
+    fn test_rotate_recovery() {
+        let blob = create_vault(VaultType::Real, "1234", "old words", low_params(), low_params()).unwrap();
+        let mut handle = unlock_vault(&blob, "1234").unwrap();
+        handle.add_entry(sample_entry("Mail"));
+
+        let rotated = rotate_recovery(&mut handle, "new words", low_params()).unwrap();
+        let (header, _, _) = format::split(&rotated).unwrap();
+        assert_eq!(header.methods.len(), 2);
+        assert_eq!(header.methods[0].method_id, UnlockMethodType::Pin);
+        assert_eq!(header.methods[1].method_id, UnlockMethodType::Recovery);
+
+        assert_eq!(unlock_vault(&rotated, "old words").err(), Some(RichieSafeError::WrongSecret));
+        assert_eq!(unlock_vault(&rotated, "new words").unwrap().state.entries.len(), 1);
+        assert!(unlock_vault(&rotated, "1234").is_ok());
+    }
+
+    #[test]
+    fn test_rekey_vault_retires_old_key() {
+        let blob = create_vault(VaultType::Real, "1234", "rec", low_params(), low_params()).unwrap();
+        let mut handle = unlock_vault(&blob, "1234").unwrap();
+        let entry_id = handle.add_entry(sample_entry("Mail"));
+        let (reference, att_blob) = handle.add_attachment(&entry_id, "a.txt", None, b"attached").unwrap();
+        let old_key = handle.vault_key;
+
+        // Secrets must match their methods, and all attachments must be supplied
+        assert_eq!(rekey_vault(&mut handle, &["rec", "1234"], &[]).err(), Some(RichieSafeError::WrongSecret));
+        assert!(rekey_vault(&mut handle, &["1234"], &[]).is_err());
+        assert_eq!(
+            rekey_vault(&mut handle, &["1234", "rec"], &[]).err(),
+            Some(RichieSafeError::AttachmentNotFound(reference.id))
+        );
+        assert_eq!(handle.vault_key, old_key);
+
+        let out = rekey_vault(&mut handle, &["1234", "rec"], &[(reference.id, att_blob.clone())]).unwrap();
+        assert_ne!(handle.vault_key, old_key);
+
+        // The old key opens neither the new body nor the new attachment
+        let (header, nonce, ct) = format::split(&out.blob).unwrap();
+        assert_eq!(open_body(&header, &old_key, nonce, ct).err(), Some(RichieSafeError::BodyDecryption));
+        let new_att = &out.attachments[0].1;
+        assert!(attachment::open(&old_key, &reference.id, new_att).is_err());
+
+        // Both secrets still unlock, and content survived
+        let reopened = unlock_vault(&out.blob, "rec").unwrap();
+        assert_eq!(reopened.vault_key, handle.vault_key);
+        assert_eq!(reopened.open_attachment(&reference.id, new_att).unwrap(), b"attached");
+        assert!(reopened.open_attachment(&reference.id, &att_blob).is_err());
+        assert!(unlock_vault(&out.blob, "1234").is_ok());
+    }
 }
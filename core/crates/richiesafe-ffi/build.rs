@@ -0,0 +1,24 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).expect("cbindgen.toml");
+
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=src");
+
+    let bindings = cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("unable to generate C header");
+
+    // Normal builds never touch the source tree; the checked-in copy is refreshed
+    // with `cargo build -p richiesafe-ffi --features header`.
+    bindings.write_to_file(out_dir.join("richiesafe.h"));
+    if env::var_os("CARGO_FEATURE_HEADER").is_some() {
+        bindings.write_to_file(crate_dir.join("include/richiesafe.h"));
+    }
+}
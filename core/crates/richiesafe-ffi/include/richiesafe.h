@@ -0,0 +1,203 @@
+/* Generated by cbindgen from richiesafe-ffi. Do not edit. */
+
+#ifndef RICHIESAFE_H
+#define RICHIESAFE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Status codes returned across the C boundary.
+// Values mirror `RichieSafeError::code()` and never change once published.
+enum RsStatus
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : int32_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  RS_STATUS_OK = 0,
+  RS_STATUS_WRONG_SECRET = 1,
+  RS_STATUS_CORRUPTED_HEADER = 2,
+  RS_STATUS_UNSUPPORTED_VERSION = 3,
+  RS_STATUS_TRUNCATED_BLOB = 4,
+  RS_STATUS_BODY_DECRYPTION = 5,
+  RS_STATUS_CBOR_DECODE = 6,
+  RS_STATUS_CBOR_ENCODE = 7,
+  RS_STATUS_KDF = 8,
+  RS_STATUS_ENCRYPTION = 9,
+  RS_STATUS_DECRYPTION = 10,
+  RS_STATUS_MISSING_UNLOCK_METHOD = 11,
+  RS_STATUS_ENTRY_NOT_FOUND = 12,
+  RS_STATUS_UNSUPPORTED_SCHEMA = 13,
+  RS_STATUS_ATTACHMENT_NOT_FOUND = 14,
+  RS_STATUS_ATTACHMENT_DECRYPTION = 15,
+  RS_STATUS_PIN_VERIFICATION_FAILED = 16,
+  RS_STATUS_TORN_WRITE = 17,
+  RS_STATUS_IO = 18,
+  RS_STATUS_KDF_OUT_OF_POLICY = 19,
+  RS_STATUS_HEADER_TAMPERED = 20,
+  RS_STATUS_UNSUPPORTED_KDF = 21,
+  RS_STATUS_UNSUPPORTED_AEAD = 22,
+  RS_STATUS_THROTTLED = 23,
+  RS_STATUS_LOCKED_OUT = 24,
+  RS_STATUS_RECOVERY_REQUIRED = 25,
+  RS_STATUS_ATTEMPT_RECORD_TAMPERED = 26,
+  RS_STATUS_WEAK_SECRET = 27,
+  RS_STATUS_INVALID_GENERATOR_OPTIONS = 28,
+  RS_STATUS_INVALID_OTP = 29,
+  RS_STATUS_OTP_NOT_CONFIGURED = 30,
+  RS_STATUS_INVALID_FIELD = 31,
+  // Null pointer, invalid UTF-8, malformed UUID or JSON. FFI-only.
+  RS_STATUS_INVALID_ARGUMENT = 100,
+  // A Rust panic was caught at the boundary. FFI-only.
+  RS_STATUS_PANIC = 101,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum RsStatus RsStatus;
+#else
+typedef int32_t RsStatus;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+// Opaque unlocked vault. Holds the vault key until `rs_vault_free`.
+typedef struct RsVault RsVault;
+
+// Bytes owned by the library and handed to the caller.
+// Must be released with `rs_buffer_free`, which wipes the contents first.
+typedef struct RsBuffer {
+  uint8_t *data;
+  size_t len;
+} RsBuffer;
+
+// Argon2id cost for `rs_vault_create`. Pass null to calibrate on this device.
+typedef struct RsKdfParams {
+  // Memory in KiB
+  uint32_t m_cost;
+  uint32_t t_cost;
+  uint32_t p_cost;
+} RsKdfParams;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Stable upper-snake name of a status code (e.g. `"WRONG_SECRET"`), or `"UNKNOWN"`.
+// The returned string is static and must not be freed.
+const char *rs_status_name(int32_t status);
+
+// Wipes and frees a buffer returned by this library. Null buffers are ignored.
+//
+// # Safety
+// `buf` must come from this library and must not be freed twice.
+void rs_buffer_free(struct RsBuffer buf);
+
+// Creates a new vault unlockable by `pin` or `recovery` and writes the sealed blob to `out_blob`.
+// `vault_type` is 1 for a real vault, 2 for a decoy. `kdf` may be null.
+//
+// # Safety
+// `pin` and `recovery` must be NUL-terminated UTF-8, `kdf` must be null or valid
+// and `out_blob` must be writable.
+RsStatus rs_vault_create(uint8_t vault_type,
+                         const char *pin,
+                         const char *recovery,
+                         const struct RsKdfParams *kdf,
+                         struct RsBuffer *out_blob);
+
+// Unlocks `blob` with a PIN or recovery phrase. On success `*out_vault` owns the
+// unlocked vault and must be released with `rs_vault_free`.
+//
+// # Safety
+// `blob` must point to `blob_len` readable bytes, `secret` must be NUL-terminated
+// UTF-8 and `out_vault` must be writable.
+RsStatus rs_vault_unlock(const uint8_t *blob,
+                         size_t blob_len,
+                         const char *secret,
+                         struct RsVault **out_vault);
+
+// Like `rs_vault_unlock`, but throttled by a failed-attempt record (default policy).
+// `record` is the last record written for this vault, or null for none; one that
+// fails to verify under `device_key` counts as the limit being reached.
+// `*out_record` receives the updated record whatever the status, and the host must
+// store it before the next attempt. Wrong guesses fail with `RS_STATUS_WRONG_SECRET`,
+// then `RS_STATUS_THROTTLED`, `RS_STATUS_RECOVERY_REQUIRED` or `RS_STATUS_LOCKED_OUT`.
+//
+// # Safety
+// `blob`, `device_key` and (when not null) `record` must point to that many readable
+// bytes, `secret` must be NUL-terminated UTF-8 and both out-pointers must be writable.
+RsStatus rs_vault_unlock_guarded(const uint8_t *blob,
+                                 size_t blob_len,
+                                 const char *secret,
+                                 const uint8_t *device_key,
+                                 size_t device_key_len,
+                                 const uint8_t *record,
+                                 size_t record_len,
+                                 struct RsBuffer *out_record,
+                                 struct RsVault **out_vault);
+
+// Encrypts the current state under the vault's header and writes the blob to `out_blob`.
+//
+// # Safety
+// `vault` must come from `rs_vault_unlock`; `out_blob` must be writable.
+RsStatus rs_vault_seal(const struct RsVault *vault, struct RsBuffer *out_blob);
+
+// Locks the vault: wipes the key and decrypted entries. Null is ignored.
+//
+// # Safety
+// `vault` must come from `rs_vault_unlock` and must not be used afterwards.
+void rs_vault_free(struct RsVault *vault);
+
+// Writes a JSON array of `{id, title, kind, tags, updated_at}` to `out_json`.
+//
+// # Safety
+// `vault` must come from `rs_vault_unlock`; `out_json` must be writable.
+RsStatus rs_vault_list_entries(const struct RsVault *vault, struct RsBuffer *out_json);
+
+// Writes the full entry, secrets included, as JSON to `out_json`.
+//
+// # Safety
+// `vault` must come from `rs_vault_unlock`, `id` must be a NUL-terminated UUID
+// string and `out_json` must be writable.
+RsStatus rs_vault_get_entry(const struct RsVault *vault, const char *id, struct RsBuffer *out_json);
+
+// Adds an entry described by `entry_json` (`{title, kind, notes, tags}`, with `kind`
+// tagged by `type`) and writes its new UUID string to `out_id`. The change is in memory until `rs_vault_seal`.
+//
+// # Safety
+// `vault` must come from `rs_vault_unlock`, `entry_json` must be NUL-terminated
+// UTF-8 and `out_id` must be writable.
+RsStatus rs_vault_add_entry(struct RsVault *vault,
+                            const char *entry_json,
+                            struct RsBuffer *out_id);
+
+// Replaces the title, kind, notes, tags and (when given) custom fields of an
+// entry. Attachments and `created_at` are kept.
+//
+// # Safety
+// `vault` must come from `rs_vault_unlock`; `id` and `entry_json` must be
+// NUL-terminated UTF-8.
+RsStatus rs_vault_update_entry(struct RsVault *vault, const char *id, const char *entry_json);
+
+// Removes an entry.
+//
+// # Safety
+// `vault` must come from `rs_vault_unlock`; `id` must be a NUL-terminated UUID string.
+RsStatus rs_vault_delete_entry(struct RsVault *vault, const char *id);
+
+// Writes `{"entropy_bits":…,"score":0-4,"reasons":["sequence",…]}` for `secret`
+// to `out_json`.
+//
+// # Safety
+// `secret` must be NUL-terminated UTF-8 and `out_json` must be writable.
+RsStatus rs_estimate_strength(const char *secret, struct RsBuffer *out_json);
+
+// Sets the process-wide requirements for new PINs (`rs_vault_create` and PIN
+// changes fail with `WeakSecret` below them). `0, 0` accepts anything.
+RsStatus rs_set_strength_policy(uint32_t min_length, uint8_t min_score);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RICHIESAFE_H */
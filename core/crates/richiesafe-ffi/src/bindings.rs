@@ -0,0 +1,467 @@
+//! UniFFI interface (Kotlin, Swift). Mirrors the C ABI in `vault` but with typed
+//! records instead of JSON buffers.
+
+use richiesafe_core::crypto::kdf::{self, KdfParams};
+use richiesafe_core::models::entry::{
+    CardData, CustomField, EntryKind, FieldType, FileData, IdentityData, LoginData, NoteData, VaultEntry,
+};
+use richiesafe_core::strength::{self, StrengthPolicy};
+use richiesafe_core::vault::attempts::{self, AttemptRecord, ThrottlePolicy};
+use richiesafe_core::vault::header::VaultType;
+use richiesafe_core::vault::ops::{self, VaultHandle};
+use richiesafe_core::RichieSafeError;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum VaultError {
+    /// A core error. `code` and `name` match `RichieSafeError::code()` / `code_name()`.
+    #[error("{message}")]
+    Core { code: i32, name: String, message: String },
+    #[error("invalid argument: {message}")]
+    InvalidArgument { message: String },
+}
+
+impl From<RichieSafeError> for VaultError {
+    fn from(e: RichieSafeError) -> Self {
+        VaultError::Core {
+            code: e.code() as i32,
+            name: e.code_name().to_string(),
+            message: e.to_string(),
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, VaultError>;
+
+#[derive(uniffi::Enum)]
+pub enum VaultKind {
+    Real,
+    Decoy,
+}
+
+/// Argon2id cost; `None` arguments calibrate on the current device.
+#[derive(uniffi::Record)]
+pub struct KdfConfig {
+    /// Memory in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+fn kdf_params(config: Option<KdfConfig>) -> Result<KdfParams> {
+    Ok(match config {
+        Some(c) => KdfParams { m_cost: c.m_cost, t_cost: c.t_cost, p_cost: c.p_cost },
+        None => kdf::calibrate(kdf::DEFAULT_TARGET_MS, kdf::DEFAULT_MAX_MEMORY_KIB)?,
+    })
+}
+
+#[derive(uniffi::Enum)]
+pub enum EntryContent {
+    Login { username: String, password: Option<String>, url: Option<String> },
+    Card { holder: Option<String>, number: String, expiry: Option<String>, cvv: Option<String> },
+    Note { text: String },
+    Identity {
+        full_name: String,
+        email: Option<String>,
+        phone: Option<String>,
+        address: Option<String>,
+        birth_date: Option<String>,
+        document_number: Option<String>,
+    },
+    File { file_name: String, mime_type: Option<String>, data: Vec<u8> },
+}
+
+impl From<&EntryKind> for EntryContent {
+    fn from(kind: &EntryKind) -> Self {
+        match kind {
+            EntryKind::Login(l) => EntryContent::Login {
+                username: l.username.clone(),
+                password: l.password.clone(),
+                url: l.url.clone(),
+            },
+            EntryKind::Card(c) => EntryContent::Card {
+                holder: c.holder.clone(),
+                number: c.number.clone(),
+                expiry: c.expiry.clone(),
+                cvv: c.cvv.clone(),
+            },
+            EntryKind::Note(n) => EntryContent::Note { text: n.text.clone() },
+            EntryKind::Identity(i) => EntryContent::Identity {
+                full_name: i.full_name.clone(),
+                email: i.email.clone(),
+                phone: i.phone.clone(),
+                address: i.address.clone(),
+                birth_date: i.birth_date.clone(),
+                document_number: i.document_number.clone(),
+            },
+            EntryKind::File(f) => EntryContent::File {
+                file_name: f.file_name.clone(),
+                mime_type: f.mime_type.clone(),
+                data: f.data.clone(),
+            },
+        }
+    }
+}
+
+impl From<EntryContent> for EntryKind {
+    fn from(content: EntryContent) -> Self {
+        match content {
+            EntryContent::Login { username, password, url } => {
+                EntryKind::Login(LoginData { username, password, url })
+            }
+            EntryContent::Card { holder, number, expiry, cvv } => {
+                EntryKind::Card(CardData { holder, number, expiry, cvv })
+            }
+            EntryContent::Note { text } => EntryKind::Note(NoteData { text }),
+            EntryContent::Identity { full_name, email, phone, address, birth_date, document_number } => {
+                EntryKind::Identity(IdentityData { full_name, email, phone, address, birth_date, document_number })
+            }
+            EntryContent::File { file_name, mime_type, data } => {
+                EntryKind::File(FileData { file_name, mime_type, data })
+            }
+        }
+    }
+}
+
+/// Mirrors `FieldType`.
+#[derive(uniffi::Enum, Clone, Copy)]
+pub enum FieldKind {
+    Text,
+    Hidden,
+    Url,
+    Email,
+    /// `YYYY-MM-DD`
+    Date,
+    /// `true` or `false`
+    Boolean,
+}
+
+impl From<FieldType> for FieldKind {
+    fn from(t: FieldType) -> Self {
+        match t {
+            FieldType::Text => FieldKind::Text,
+            FieldType::Hidden => FieldKind::Hidden,
+            FieldType::Url => FieldKind::Url,
+            FieldType::Email => FieldKind::Email,
+            FieldType::Date => FieldKind::Date,
+            FieldType::Boolean => FieldKind::Boolean,
+        }
+    }
+}
+
+impl From<FieldKind> for FieldType {
+    fn from(k: FieldKind) -> Self {
+        match k {
+            FieldKind::Text => FieldType::Text,
+            FieldKind::Hidden => FieldType::Hidden,
+            FieldKind::Url => FieldType::Url,
+            FieldKind::Email => FieldType::Email,
+            FieldKind::Date => FieldType::Date,
+            FieldKind::Boolean => FieldType::Boolean,
+        }
+    }
+}
+
+/// A user-defined field, in the order the user arranged them.
+#[derive(uniffi::Record)]
+pub struct EntryField {
+    pub name: String,
+    pub field_type: FieldKind,
+    pub value: String,
+}
+
+/// Builds core fields through `CustomField::new`, so bad values are refused up front.
+fn custom_fields(fields: Vec<EntryField>) -> Result<Vec<CustomField>> {
+    fields
+        .into_iter()
+        .map(|f| Ok(CustomField::new(&f.name, f.field_type.into(), f.value)?))
+        .collect()
+}
+
+/// Input for `Vault::add_entry` and `Vault::update_entry`.
+#[derive(uniffi::Record)]
+pub struct NewEntry {
+    pub title: String,
+    pub content: EntryContent,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    /// `None` leaves an existing entry's fields untouched on update
+    pub fields: Option<Vec<EntryField>>,
+}
+
+#[derive(uniffi::Record)]
+pub struct Entry {
+    pub id: String,
+    pub title: String,
+    pub content: EntryContent,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    pub fields: Vec<EntryField>,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+impl From<&VaultEntry> for Entry {
+    fn from(e: &VaultEntry) -> Self {
+        Entry {
+            id: e.id.to_string(),
+            title: e.title.clone(),
+            content: EntryContent::from(&e.kind),
+            notes: e.notes.clone(),
+            tags: e.tags.clone(),
+            fields: e
+                .fields
+                .iter()
+                .map(|f| EntryField { name: f.name.clone(), field_type: f.field_type.into(), value: f.value.clone() })
+                .collect(),
+            created_at: e.created_at.into(),
+            updated_at: e.updated_at.into(),
+        }
+    }
+}
+
+/// List view of an entry; carries no secret fields.
+#[derive(uniffi::Record)]
+pub struct EntrySummary {
+    pub id: String,
+    pub title: String,
+    /// Lowercase kind name (`login`, `card`, `note`, `identity`, `file`)
+    pub kind: String,
+    pub tags: Vec<String>,
+    pub updated_at: SystemTime,
+}
+
+fn parse_id(id: &str) -> Result<Uuid> {
+    Uuid::parse_str(id).map_err(|e| VaultError::InvalidArgument { message: e.to_string() })
+}
+
+/// Result of `estimate_strength`.
+#[derive(uniffi::Record)]
+pub struct StrengthEstimate {
+    /// log2 of the estimated number of guesses
+    pub entropy_bits: f64,
+    /// 0 (guessed almost at once) to 4 (strong)
+    pub score: u8,
+    /// Patterns found: `short`, `repeat`, `sequence`, `keyboard_walk`, `date`, `common_word`
+    pub reasons: Vec<String>,
+}
+
+#[uniffi::export]
+pub fn estimate_strength(secret: String) -> StrengthEstimate {
+    let estimate = strength::estimate(&secret);
+    StrengthEstimate {
+        entropy_bits: estimate.entropy_bits,
+        score: estimate.score,
+        reasons: estimate.reasons.iter().map(|r| r.name().to_string()).collect(),
+    }
+}
+
+/// Sets the requirements for new PINs in `create_vault` and `Vault::change_pin`.
+/// `0, 0` accepts anything.
+#[uniffi::export]
+pub fn set_strength_policy(min_length: u32, min_score: u8) -> Result<()> {
+    if min_score > 4 {
+        return Err(VaultError::InvalidArgument { message: "min_score is 0-4".into() });
+    }
+    strength::set_policy(StrengthPolicy { min_length: min_length as usize, min_score });
+    Ok(())
+}
+
+/// Creates a vault unlockable by `pin` or `recovery` and returns the sealed blob.
+#[uniffi::export]
+pub fn create_vault(vault_kind: VaultKind, pin: String, recovery: String, kdf: Option<KdfConfig>) -> Result<Vec<u8>> {
+    let vault_type = match vault_kind {
+        VaultKind::Real => VaultType::Real,
+        VaultKind::Decoy => VaultType::Decoy,
+    };
+    let params = kdf_params(kdf)?;
+    Ok(ops::create_vault(vault_type, &pin, &recovery, params, params)?)
+}
+
+/// Unlocks a blob with a PIN or recovery phrase, unthrottled. Lock screens use
+/// `AttemptGuard` instead.
+#[uniffi::export]
+pub fn unlock_vault(blob: Vec<u8>, secret: String) -> Result<Arc<Vault>> {
+    let inner = ops::unlock_vault(&blob, &secret)?;
+    Ok(Arc::new(Vault { inner: Mutex::new(inner) }))
+}
+
+/// Failed-unlock throttling for one vault (default policy). The host keeps
+/// `record()` next to the vault blob and stores it after every `unlock` call,
+/// whether it succeeded or not. `device_key` should live in the Keystore / Keychain.
+#[derive(uniffi::Object)]
+pub struct AttemptGuard {
+    record: Mutex<AttemptRecord>,
+    device_key: Vec<u8>,
+}
+
+#[uniffi::export]
+impl AttemptGuard {
+    /// `stored` is the last saved record, or `None` for a vault never unlocked on this
+    /// device. A stored record that fails to verify locks as if the limit was reached.
+    #[uniffi::constructor]
+    pub fn new(blob: Vec<u8>, device_key: Vec<u8>, stored: Option<Vec<u8>>) -> Result<Arc<Self>> {
+        let policy = ThrottlePolicy::default();
+        let record = match stored {
+            Some(bytes) => match AttemptRecord::from_bytes(&bytes, &device_key, &blob) {
+                Err(RichieSafeError::AttemptRecordTampered) => AttemptRecord::tripped(&blob, &policy),
+                other => other,
+            },
+            None => AttemptRecord::new(&blob),
+        }?;
+        Ok(Arc::new(AttemptGuard { record: Mutex::new(record), device_key }))
+    }
+
+    pub fn unlock(&self, blob: Vec<u8>, secret: String) -> Result<Arc<Vault>> {
+        let mut record = self.record.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let unlocked = attempts::unlock(&blob, &secret, &mut record, &ThrottlePolicy::default())?;
+        Ok(Arc::new(Vault { inner: Mutex::new(unlocked.handle) }))
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.record.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).failures()
+    }
+
+    /// Serialized record to persist.
+    pub fn record(&self) -> Vec<u8> {
+        self.record.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).to_bytes(&self.device_key)
+    }
+}
+
+/// An unlocked vault. The key is wiped when the host releases the object
+/// (`close()` in Kotlin, deinit in Swift).
+#[derive(uniffi::Object)]
+pub struct Vault {
+    inner: Mutex<VaultHandle>,
+}
+
+impl Vault {
+    fn handle(&self) -> MutexGuard<'_, VaultHandle> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[uniffi::export]
+impl Vault {
+    pub fn list_entries(&self) -> Vec<EntrySummary> {
+        self.handle().state.entries.iter()
+            .map(|e| EntrySummary {
+                id: e.id.to_string(),
+                title: e.title.clone(),
+                kind: e.kind.name().to_string(),
+                tags: e.tags.clone(),
+                updated_at: e.updated_at.into(),
+            })
+            .collect()
+    }
+
+    pub fn get_entry(&self, id: String) -> Result<Entry> {
+        let id = parse_id(&id)?;
+        Ok(Entry::from(self.handle().get_entry(&id)?))
+    }
+
+    /// Returns the new entry id. Changes are in memory until `seal`.
+    pub fn add_entry(&self, entry: NewEntry) -> Result<String> {
+        let fields = custom_fields(entry.fields.unwrap_or_default())?;
+        let mut new = VaultEntry::new(&entry.title, entry.content.into());
+        new.notes = entry.notes;
+        new.tags = entry.tags;
+        new.fields = fields;
+        Ok(self.handle().add_entry(new).to_string())
+    }
+
+    /// Replaces title, content, notes, tags and, when given, custom fields;
+    /// attachments and `created_at` are kept.
+    pub fn update_entry(&self, id: String, entry: NewEntry) -> Result<()> {
+        let id = parse_id(&id)?;
+        let fields = entry.fields.map(custom_fields).transpose()?;
+        self.handle().update_entry(&id, |e| {
+            e.title = entry.title;
+            e.kind = entry.content.into();
+            e.notes = entry.notes;
+            e.tags = entry.tags;
+            if let Some(fields) = fields {
+                e.fields = fields;
+            }
+        })?;
+        Ok(())
+    }
+
+    pub fn delete_entry(&self, id: String) -> Result<()> {
+        let id = parse_id(&id)?;
+        Ok(self.handle().delete_entry(&id)?)
+    }
+
+    pub fn seal(&self) -> Result<Vec<u8>> {
+        Ok(self.handle().seal()?)
+    }
+
+    /// Re-wraps the key for a new PIN and returns the resealed blob.
+    pub fn change_pin(&self, old_pin: String, new_pin: String, kdf: Option<KdfConfig>) -> Result<Vec<u8>> {
+        Ok(ops::change_pin(&mut self.handle(), &old_pin, &new_pin, kdf_params(kdf)?)?)
+    }
+
+    /// Replaces the recovery phrase and returns the resealed blob.
+    pub fn rotate_recovery(&self, new_recovery: String, kdf: Option<KdfConfig>) -> Result<Vec<u8>> {
+        Ok(ops::rotate_recovery(&mut self.handle(), &new_recovery, kdf_params(kdf)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_entries_roundtrip() {
+        let fast = || Some(KdfConfig { m_cost: 19 * 1024, t_cost: 2, p_cost: 1 });
+        let blob = create_vault(VaultKind::Real, "1234".into(), "words".into(), fast()).unwrap();
+        assert!(matches!(unlock_vault(blob.clone(), "0000".into()), Err(VaultError::Core { code: 1, .. })));
+
+        let vault = unlock_vault(blob, "1234".into()).unwrap();
+        let id = vault.add_entry(NewEntry {
+            title: "Visa".into(),
+            content: EntryContent::Card { holder: None, number: "4111".into(), expiry: Some("12/29".into()), cvv: Some("123".into()) },
+            notes: None,
+            tags: vec![],
+            fields: Some(vec![EntryField { name: "PIN".into(), field_type: FieldKind::Hidden, value: "0042".into() }]),
+        }).unwrap();
+        let vault = unlock_vault(vault.seal().unwrap(), "words".into()).unwrap();
+
+        let entry = vault.get_entry(id.clone()).unwrap();
+        match entry.content {
+            EntryContent::Card { number, cvv, .. } => assert_eq!((number.as_str(), cvv.as_deref()), ("4111", Some("123"))),
+            _ => panic!("expected a card"),
+        }
+        assert_eq!((entry.fields[0].name.as_str(), entry.fields[0].value.as_str()), ("PIN", "0042"));
+        assert_eq!(vault.list_entries()[0].kind, "card");
+
+        // `None` keeps the fields; a bad value is refused before anything changes
+        let note = |fields| NewEntry { title: "Visa".into(), content: EntryContent::Note { text: String::new() }, notes: None, tags: vec![], fields };
+        vault.update_entry(id.clone(), note(None)).unwrap();
+        assert_eq!(vault.get_entry(id.clone()).unwrap().fields.len(), 1);
+        let bad = vec![EntryField { name: "Admin".into(), field_type: FieldKind::Boolean, value: "yes".into() }];
+        assert!(matches!(vault.update_entry(id.clone(), note(Some(bad))), Err(VaultError::Core { code: 31, .. })));
+        assert_eq!(vault.get_entry(id.clone()).unwrap().fields.len(), 1);
+        assert!(matches!(vault.get_entry("nope".into()), Err(VaultError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn attempt_guard_throttles_and_persists() {
+        let fast = Some(KdfConfig { m_cost: 19 * 1024, t_cost: 2, p_cost: 1 });
+        let blob = create_vault(VaultKind::Real, "1234".into(), "words".into(), fast).unwrap();
+
+        let guard = AttemptGuard::new(blob.clone(), b"device".to_vec(), None).unwrap();
+        for _ in 0..4 {
+            assert!(matches!(guard.unlock(blob.clone(), "0000".into()), Err(VaultError::Core { code: 1, .. })));
+        }
+        assert!(matches!(guard.unlock(blob.clone(), "1234".into()), Err(VaultError::Core { code: 23, .. })));
+
+        // A fresh guard from the stored record carries the count; a forged one fails closed
+        let restored = AttemptGuard::new(blob.clone(), b"device".to_vec(), Some(guard.record())).unwrap();
+        assert_eq!(restored.failures(), 4);
+        let forged = AttemptGuard::new(blob.clone(), b"other".to_vec(), Some(guard.record())).unwrap();
+        assert!(forged.unlock(blob, "1234".into()).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use std::ptr;
+use zeroize::Zeroize;
+
+/// Bytes owned by the library and handed to the caller.
+/// Must be released with `rs_buffer_free`, which wipes the contents first.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RsBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl RsBuffer {
+    pub const EMPTY: RsBuffer = RsBuffer { data: ptr::null_mut(), len: 0 };
+
+    pub(crate) fn from_vec(bytes: Vec<u8>) -> Self {
+        let boxed = bytes.into_boxed_slice();
+        let len = boxed.len();
+        RsBuffer { data: Box::into_raw(boxed) as *mut u8, len }
+    }
+}
+
+/// Wipes and frees a buffer returned by this library. Null buffers are ignored.
+///
+/// # Safety
+/// `buf` must come from this library and must not be freed twice.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_buffer_free(buf: RsBuffer) {
+    if buf.data.is_null() {
+        return;
+    }
+    let mut boxed = unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(buf.data, buf.len)) };
+    boxed.zeroize();
+}
@@ -1,14 +1,352 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+//! C ABI for native hosts (Android, iOS, desktop).
+//!
+//! Every function returns an `RsStatus`. Outputs are written through out-pointers;
+//! byte outputs are `RsBuffer`s owned by the caller and released with
+//! `rs_buffer_free`. The checked-in `include/richiesafe.h` is regenerated with
+//! `cargo build -p richiesafe-ffi --features header`.
+//!
+//! The same operations are exported through UniFFI in `bindings` for Kotlin and Swift.
+
+pub mod bindings;
+mod buffer;
+mod vault;
+
+pub use buffer::{rs_buffer_free, RsBuffer};
+pub use vault::*;
+
+use richiesafe_core::RichieSafeError;
+use std::ffi::{c_char, CStr};
+use std::panic::{self, AssertUnwindSafe};
+
+uniffi::setup_scaffolding!();
+
+/// Status codes returned across the C boundary.
+/// Values mirror `RichieSafeError::code()` and never change once published.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RsStatus {
+    Ok = 0,
+    WrongSecret = 1,
+    CorruptedHeader = 2,
+    UnsupportedVersion = 3,
+    TruncatedBlob = 4,
+    BodyDecryption = 5,
+    CborDecode = 6,
+    CborEncode = 7,
+    Kdf = 8,
+    Encryption = 9,
+    Decryption = 10,
+    MissingUnlockMethod = 11,
+    EntryNotFound = 12,
+    UnsupportedSchema = 13,
+    AttachmentNotFound = 14,
+    AttachmentDecryption = 15,
+    PinVerificationFailed = 16,
+    TornWrite = 17,
+    Io = 18,
+    KdfOutOfPolicy = 19,
+    HeaderTampered = 20,
+    UnsupportedKdf = 21,
+    UnsupportedAead = 22,
+    Throttled = 23,
+    LockedOut = 24,
+    RecoveryRequired = 25,
+    AttemptRecordTampered = 26,
+    WeakSecret = 27,
+    InvalidGeneratorOptions = 28,
+    InvalidOtp = 29,
+    OtpNotConfigured = 30,
+    InvalidField = 31,
+    /// Null pointer, invalid UTF-8, malformed UUID or JSON. FFI-only.
+    InvalidArgument = 100,
+    /// A Rust panic was caught at the boundary. FFI-only.
+    Panic = 101,
+}
+
+impl From<&RichieSafeError> for RsStatus {
+    fn from(e: &RichieSafeError) -> Self {
+        match e {
+            RichieSafeError::WrongSecret => RsStatus::WrongSecret,
+            RichieSafeError::CorruptedHeader(_) => RsStatus::CorruptedHeader,
+            RichieSafeError::UnsupportedVersion(_) => RsStatus::UnsupportedVersion,
+            RichieSafeError::TruncatedBlob => RsStatus::TruncatedBlob,
+            RichieSafeError::BodyDecryption => RsStatus::BodyDecryption,
+            RichieSafeError::CborDecode(_) => RsStatus::CborDecode,
+            RichieSafeError::CborEncode(_) => RsStatus::CborEncode,
+            RichieSafeError::Kdf(_) => RsStatus::Kdf,
+            RichieSafeError::Encryption => RsStatus::Encryption,
+            RichieSafeError::Decryption => RsStatus::Decryption,
+            RichieSafeError::MissingUnlockMethod(_) => RsStatus::MissingUnlockMethod,
+            RichieSafeError::EntryNotFound(_) => RsStatus::EntryNotFound,
+            RichieSafeError::UnsupportedSchema(_) => RsStatus::UnsupportedSchema,
+            RichieSafeError::AttachmentNotFound(_) => RsStatus::AttachmentNotFound,
+            RichieSafeError::AttachmentDecryption => RsStatus::AttachmentDecryption,
+            RichieSafeError::PinVerificationFailed => RsStatus::PinVerificationFailed,
+            RichieSafeError::TornWrite => RsStatus::TornWrite,
+            RichieSafeError::Io(_) => RsStatus::Io,
+            RichieSafeError::KdfOutOfPolicy(_) => RsStatus::KdfOutOfPolicy,
+            RichieSafeError::HeaderTampered => RsStatus::HeaderTampered,
+            RichieSafeError::UnsupportedKdf(_) => RsStatus::UnsupportedKdf,
+            RichieSafeError::UnsupportedAead(_) => RsStatus::UnsupportedAead,
+            RichieSafeError::Throttled(_) => RsStatus::Throttled,
+            RichieSafeError::LockedOut => RsStatus::LockedOut,
+            RichieSafeError::RecoveryRequired => RsStatus::RecoveryRequired,
+            RichieSafeError::AttemptRecordTampered => RsStatus::AttemptRecordTampered,
+            RichieSafeError::WeakSecret(_) => RsStatus::WeakSecret,
+            RichieSafeError::InvalidGeneratorOptions(_) => RsStatus::InvalidGeneratorOptions,
+            RichieSafeError::InvalidOtp(_) => RsStatus::InvalidOtp,
+            RichieSafeError::OtpNotConfigured(_) => RsStatus::OtpNotConfigured,
+            RichieSafeError::InvalidField(_) => RsStatus::InvalidField,
+        }
+    }
+}
+
+impl From<RichieSafeError> for RsStatus {
+    fn from(e: RichieSafeError) -> Self {
+        RsStatus::from(&e)
+    }
+}
+
+/// Runs an FFI body, turning errors into status codes and panics into `RsStatus::Panic`.
+pub(crate) fn guard<F: FnOnce() -> Result<(), RsStatus>>(f: F) -> RsStatus {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => RsStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => RsStatus::Panic,
+    }
+}
+
+/// Stable upper-snake name of a status code (e.g. `"WRONG_SECRET"`), or `"UNKNOWN"`.
+/// The returned string is static and must not be freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn rs_status_name(status: i32) -> *const c_char {
+    let name: &'static CStr = match status {
+        0 => c"OK",
+        1 => c"WRONG_SECRET",
+        2 => c"CORRUPTED_HEADER",
+        3 => c"UNSUPPORTED_VERSION",
+        4 => c"TRUNCATED_BLOB",
+        5 => c"BODY_DECRYPTION",
+        6 => c"CBOR_DECODE",
+        7 => c"CBOR_ENCODE",
+        8 => c"KDF",
+        9 => c"ENCRYPTION",
+        10 => c"DECRYPTION",
+        11 => c"MISSING_UNLOCK_METHOD",
+        12 => c"ENTRY_NOT_FOUND",
+        13 => c"UNSUPPORTED_SCHEMA",
+        14 => c"ATTACHMENT_NOT_FOUND",
+        15 => c"ATTACHMENT_DECRYPTION",
+        16 => c"PIN_VERIFICATION_FAILED",
+        17 => c"TORN_WRITE",
+        18 => c"IO",
+        19 => c"KDF_OUT_OF_POLICY",
+        20 => c"HEADER_TAMPERED",
+        21 => c"UNSUPPORTED_KDF",
+        22 => c"UNSUPPORTED_AEAD",
+        23 => c"THROTTLED",
+        24 => c"LOCKED_OUT",
+        25 => c"RECOVERY_REQUIRED",
+        26 => c"ATTEMPT_RECORD_TAMPERED",
+        27 => c"WEAK_SECRET",
+        28 => c"INVALID_GENERATOR_OPTIONS",
+        29 => c"INVALID_OTP",
+        30 => c"OTP_NOT_CONFIGURED",
+        31 => c"INVALID_FIELD",
+        100 => c"INVALID_ARGUMENT",
+        101 => c"PANIC",
+        _ => c"UNKNOWN",
+    };
+    name.as_ptr()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use richiesafe_core::crypto::kdf::KdfParams;
+    use richiesafe_core::vault::header::VaultType;
+    use richiesafe_core::vault::ops;
+    use std::ffi::CString;
+    use std::ptr;
+
+    #[test]
+    fn checked_in_header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/richiesafe.h"));
+        let checked_in = include_str!("../include/richiesafe.h");
+        assert!(generated == checked_in, "include/richiesafe.h is stale; rebuild with --features header");
+    }
+
+    #[test]
+    fn status_matches_core_code() {
+        let errors = [
+            RichieSafeError::WrongSecret,
+            RichieSafeError::CorruptedHeader("x"),
+            RichieSafeError::UnsupportedVersion(9),
+            RichieSafeError::TruncatedBlob,
+            RichieSafeError::BodyDecryption,
+            RichieSafeError::CborDecode(String::new()),
+            RichieSafeError::CborEncode(String::new()),
+            RichieSafeError::Kdf(String::new()),
+            RichieSafeError::Encryption,
+            RichieSafeError::Decryption,
+            RichieSafeError::MissingUnlockMethod("pin"),
+            RichieSafeError::EntryNotFound(uuid::Uuid::nil()),
+            RichieSafeError::UnsupportedSchema(0),
+            RichieSafeError::AttachmentNotFound(uuid::Uuid::nil()),
+            RichieSafeError::AttachmentDecryption,
+            RichieSafeError::PinVerificationFailed,
+            RichieSafeError::TornWrite,
+            RichieSafeError::Io(String::new()),
+            RichieSafeError::KdfOutOfPolicy(String::new()),
+            RichieSafeError::HeaderTampered,
+            RichieSafeError::UnsupportedKdf(0),
+            RichieSafeError::UnsupportedAead(0),
+            RichieSafeError::Throttled(0),
+            RichieSafeError::LockedOut,
+            RichieSafeError::RecoveryRequired,
+            RichieSafeError::AttemptRecordTampered,
+            RichieSafeError::WeakSecret(String::new()),
+            RichieSafeError::InvalidGeneratorOptions(String::new()),
+            RichieSafeError::InvalidOtp(String::new()),
+            RichieSafeError::OtpNotConfigured(uuid::Uuid::nil()),
+            RichieSafeError::InvalidField(String::new()),
+        ];
+        for e in &errors {
+            assert_eq!(RsStatus::from(e) as i32, e.code() as i32);
+            assert_eq!(name(e.code() as i32), e.code_name());
+        }
+        assert_eq!(name(RsStatus::InvalidArgument as i32), "INVALID_ARGUMENT");
+        assert_eq!(name(-7), "UNKNOWN");
+    }
+
+    fn name(status: i32) -> &'static str {
+        unsafe { CStr::from_ptr(rs_status_name(status)) }.to_str().unwrap()
+    }
+
+    /// Copies a buffer out and frees it, the way a host would.
+    fn take(buf: RsBuffer) -> Vec<u8> {
+        let bytes = unsafe { std::slice::from_raw_parts(buf.data, buf.len) }.to_vec();
+        unsafe { rs_buffer_free(buf) };
+        bytes
+    }
+
+    fn unlock(blob: &[u8], secret: &CStr) -> Result<*mut RsVault, RsStatus> {
+        let mut vault = ptr::null_mut();
+        match unsafe { rs_vault_unlock(blob.as_ptr(), blob.len(), secret.as_ptr(), &mut vault) } {
+            RsStatus::Ok => Ok(vault),
+            status => Err(status),
+        }
+    }
+
+    fn seal(vault: *mut RsVault) -> Vec<u8> {
+        let mut out = RsBuffer::EMPTY;
+        assert_eq!(unsafe { rs_vault_seal(vault, &mut out) }, RsStatus::Ok);
+        take(out)
+    }
+
+    fn test_blob() -> Vec<u8> {
+        let params = KdfParams::FLOOR;
+        ops::create_vault(VaultType::Real, "1234", "recovery words", params, params).unwrap()
+    }
+
+    #[test]
+    fn guarded_unlock_carries_the_record() {
+        let blob = test_blob();
+        let key = b"device";
+        let mut record: Option<Vec<u8>> = None;
+        let mut attempt = |secret: &CStr| {
+            let (mut out_record, mut vault) = (RsBuffer::EMPTY, ptr::null_mut());
+            let (stored, stored_len) = record.as_ref().map_or((ptr::null(), 0), |r| (r.as_ptr(), r.len()));
+            let status = unsafe {
+                rs_vault_unlock_guarded(blob.as_ptr(), blob.len(), secret.as_ptr(), key.as_ptr(), key.len(), stored, stored_len, &mut out_record, &mut vault)
+            };
+            record = Some(take(out_record));
+            unsafe { rs_vault_free(vault) };
+            status
+        };
+
+        assert_eq!(attempt(c"1234"), RsStatus::Ok);
+        for _ in 0..4 {
+            assert_eq!(attempt(c"0000"), RsStatus::WrongSecret);
+        }
+        assert_eq!(attempt(c"1234"), RsStatus::Throttled);
+    }
+
+    #[test]
+    fn create_then_unlock_through_c_abi() {
+        let mut blob = RsBuffer::EMPTY;
+        let kdf = RsKdfParams { m_cost: 19 * 1024, t_cost: 2, p_cost: 1 };
+        let status = unsafe { rs_vault_create(1, c"1234".as_ptr(), c"recovery words".as_ptr(), &kdf, &mut blob) };
+        assert_eq!(status, RsStatus::Ok);
+        let blob = take(blob);
+
+        let vault = unlock(&blob, c"recovery words").unwrap();
+        unsafe { rs_vault_free(vault) };
+        assert_eq!(unlock(&blob, c"0000").unwrap_err(), RsStatus::WrongSecret);
+    }
 
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn entry_crud_through_c_abi() {
+        let vault = unlock(&test_blob(), c"1234").unwrap();
+
+        let mut id = RsBuffer::EMPTY;
+        let json = cr#"{"title":"Mail","kind":{"type":"login","username":"alice","password":"pw","url":null},"tags":["work"]}"#;
+        assert_eq!(unsafe { rs_vault_add_entry(vault, json.as_ptr(), &mut id) }, RsStatus::Ok);
+        let id = CString::new(take(id)).unwrap();
+
+        let mut list = RsBuffer::EMPTY;
+        assert_eq!(unsafe { rs_vault_list_entries(vault, &mut list) }, RsStatus::Ok);
+        let list: serde_json::Value = serde_json::from_slice(&take(list)).unwrap();
+        assert_eq!(list[0]["id"], id.to_str().unwrap());
+        assert_eq!(list[0]["kind"], "login");
+        assert!(list[0].get("password").is_none());
+
+        let update = cr#"{"title":"Mail","kind":{"type":"login","username":"alice","password":"new","url":null}}"#;
+        assert_eq!(unsafe { rs_vault_update_entry(vault, id.as_ptr(), update.as_ptr()) }, RsStatus::Ok);
+
+        // Changes survive a seal/unlock cycle
+        let blob = seal(vault);
+        unsafe { rs_vault_free(vault) };
+        let vault = unlock(&blob, c"1234").unwrap();
+
+        let mut entry = RsBuffer::EMPTY;
+        assert_eq!(unsafe { rs_vault_get_entry(vault, id.as_ptr(), &mut entry) }, RsStatus::Ok);
+        let entry: serde_json::Value = serde_json::from_slice(&take(entry)).unwrap();
+        assert_eq!(entry["kind"]["password"], "new");
+        assert!(entry["tags"].as_array().unwrap().is_empty());
+
+        assert_eq!(unsafe { rs_vault_delete_entry(vault, id.as_ptr()) }, RsStatus::Ok);
+        assert_eq!(unsafe { rs_vault_delete_entry(vault, id.as_ptr()) }, RsStatus::EntryNotFound);
+        let mut entry = RsBuffer::EMPTY;
+        assert_eq!(unsafe { rs_vault_get_entry(vault, id.as_ptr(), &mut entry) }, RsStatus::EntryNotFound);
+        assert!(entry.data.is_null());
+
+        unsafe { rs_vault_free(vault) };
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        let blob = test_blob();
+        let mut out = RsBuffer::EMPTY;
+        let mut vault = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(rs_vault_create(9, c"1".as_ptr(), c"2".as_ptr(), ptr::null(), &mut out), RsStatus::InvalidArgument);
+            assert_eq!(rs_vault_create(1, ptr::null(), c"2".as_ptr(), ptr::null(), &mut out), RsStatus::InvalidArgument);
+            assert_eq!(rs_vault_unlock(ptr::null(), 0, c"1234".as_ptr(), &mut vault), RsStatus::InvalidArgument);
+            assert_eq!(rs_vault_unlock(blob.as_ptr(), blob.len(), c"1234".as_ptr(), ptr::null_mut()), RsStatus::InvalidArgument);
+            assert_eq!(rs_vault_seal(ptr::null(), &mut out), RsStatus::InvalidArgument);
+            assert_eq!(rs_vault_unlock(blob.as_ptr(), 10, c"1234".as_ptr(), &mut vault), RsStatus::TruncatedBlob);
+        }
+        assert!(vault.is_null());
+
+        let vault = unlock(&blob, c"1234").unwrap();
+        unsafe {
+            assert_eq!(rs_vault_add_entry(vault, c"{not json".as_ptr(), &mut out), RsStatus::InvalidArgument);
+            assert_eq!(rs_vault_delete_entry(vault, c"not-a-uuid".as_ptr()), RsStatus::InvalidArgument);
+            rs_vault_free(vault);
+            rs_vault_free(ptr::null_mut());
+            rs_buffer_free(RsBuffer::EMPTY);
+        }
     }
 }
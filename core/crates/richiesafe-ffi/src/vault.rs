@@ -0,0 +1,378 @@
+use crate::buffer::RsBuffer;
+use crate::{guard, RsStatus};
+use chrono::{DateTime, Utc};
+use richiesafe_core::crypto::kdf::{self, KdfParams};
+use richiesafe_core::models::entry::{CustomField, EntryKind, VaultEntry};
+use richiesafe_core::strength::{self, StrengthPolicy};
+use richiesafe_core::vault::attempts::{self, AttemptRecord, ThrottlePolicy};
+use richiesafe_core::vault::header::VaultType;
+use richiesafe_core::RichieSafeError;
+use richiesafe_core::vault::ops::{self, VaultHandle};
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, CStr};
+use std::slice;
+use uuid::Uuid;
+
+/// Opaque unlocked vault. Holds the vault key until `rs_vault_free`.
+pub struct RsVault {
+    inner: VaultHandle,
+}
+
+/// JSON accepted by `rs_vault_add_entry` and `rs_vault_update_entry`.
+/// `kind` uses the same tagged layout as the vault body, e.g.
+/// `{"type":"login","username":"alice","password":"pw","url":null}`.
+#[derive(Deserialize)]
+struct EntryInput {
+    title: String,
+    kind: EntryKind,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Ordered `{"name":…,"type":"hidden","value":…}` list; omitted keeps the current fields
+    #[serde(default)]
+    fields: Option<Vec<CustomField>>,
+}
+
+/// Argon2id cost for `rs_vault_create`. Pass null to calibrate on this device.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RsKdfParams {
+    /// Memory in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+/// One element of the `rs_vault_list_entries` array. Carries no secret fields.
+#[derive(Serialize)]
+struct EntrySummary<'a> {
+    id: Uuid,
+    title: &'a str,
+    kind: &'static str,
+    tags: &'a [String],
+    updated_at: DateTime<Utc>,
+}
+
+unsafe fn vault_ref<'a>(vault: *const RsVault) -> Result<&'a RsVault, RsStatus> {
+    unsafe { vault.as_ref() }.ok_or(RsStatus::InvalidArgument)
+}
+
+unsafe fn vault_mut<'a>(vault: *mut RsVault) -> Result<&'a mut RsVault, RsStatus> {
+    unsafe { vault.as_mut() }.ok_or(RsStatus::InvalidArgument)
+}
+
+unsafe fn c_str<'a>(s: *const c_char) -> Result<&'a str, RsStatus> {
+    if s.is_null() {
+        return Err(RsStatus::InvalidArgument);
+    }
+    unsafe { CStr::from_ptr(s) }.to_str().map_err(|_| RsStatus::InvalidArgument)
+}
+
+unsafe fn c_id(s: *const c_char) -> Result<Uuid, RsStatus> {
+    Uuid::parse_str(unsafe { c_str(s) }?).map_err(|_| RsStatus::InvalidArgument)
+}
+
+unsafe fn out_buffer<'a>(out: *mut RsBuffer) -> Result<&'a mut RsBuffer, RsStatus> {
+    let out = unsafe { out.as_mut() }.ok_or(RsStatus::InvalidArgument)?;
+    *out = RsBuffer::EMPTY;
+    Ok(out)
+}
+
+fn parse_entry(json: &str) -> Result<EntryInput, RsStatus> {
+    serde_json::from_str(json).map_err(|_| RsStatus::InvalidArgument)
+}
+
+fn validated(fields: Option<Vec<CustomField>>) -> Result<Option<Vec<CustomField>>, RsStatus> {
+    if let Some(fields) = &fields {
+        fields.iter().try_for_each(CustomField::validate)?;
+    }
+    Ok(fields)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<RsBuffer, RsStatus> {
+    serde_json::to_vec(value).map(RsBuffer::from_vec).map_err(|_| RsStatus::InvalidArgument)
+}
+
+/// Creates a new vault unlockable by `pin` or `recovery` and writes the sealed blob to `out_blob`.
+/// `vault_type` is 1 for a real vault, 2 for a decoy. `kdf` may be null.
+///
+/// # Safety
+/// `pin` and `recovery` must be NUL-terminated UTF-8, `kdf` must be null or valid
+/// and `out_blob` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_create(
+    vault_type: u8,
+    pin: *const c_char,
+    recovery: *const c_char,
+    kdf: *const RsKdfParams,
+    out_blob: *mut RsBuffer,
+) -> RsStatus {
+    guard(|| {
+        let out = unsafe { out_buffer(out_blob) }?;
+        let vault_type = match vault_type {
+            1 => VaultType::Real,
+            2 => VaultType::Decoy,
+            _ => return Err(RsStatus::InvalidArgument),
+        };
+        let (pin, recovery) = unsafe { (c_str(pin)?, c_str(recovery)?) };
+
+        let params = match unsafe { kdf.as_ref() } {
+            Some(p) => KdfParams { m_cost: p.m_cost, t_cost: p.t_cost, p_cost: p.p_cost },
+            None => kdf::calibrate(kdf::DEFAULT_TARGET_MS, kdf::DEFAULT_MAX_MEMORY_KIB)?,
+        };
+
+        let blob = ops::create_vault(vault_type, pin, recovery, params, params)?;
+        *out = RsBuffer::from_vec(blob);
+        Ok(())
+    })
+}
+
+/// Unlocks `blob` with a PIN or recovery phrase. On success `*out_vault` owns the
+/// unlocked vault and must be released with `rs_vault_free`.
+///
+/// # Safety
+/// `blob` must point to `blob_len` readable bytes, `secret` must be NUL-terminated
+/// UTF-8 and `out_vault` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_unlock(
+    blob: *const u8,
+    blob_len: usize,
+    secret: *const c_char,
+    out_vault: *mut *mut RsVault,
+) -> RsStatus {
+    guard(|| {
+        let out = unsafe { out_vault.as_mut() }.ok_or(RsStatus::InvalidArgument)?;
+        *out = std::ptr::null_mut();
+        if blob.is_null() {
+            return Err(RsStatus::InvalidArgument);
+        }
+        let blob = unsafe { slice::from_raw_parts(blob, blob_len) };
+        let secret = unsafe { c_str(secret) }?;
+
+        let inner = ops::unlock_vault(blob, secret)?;
+        *out = Box::into_raw(Box::new(RsVault { inner }));
+        Ok(())
+    })
+}
+
+/// Like `rs_vault_unlock`, but throttled by a failed-attempt record (default policy).
+/// `record` is the last record written for this vault, or null for none; one that
+/// fails to verify under `device_key` counts as the limit being reached.
+/// `*out_record` receives the updated record whatever the status, and the host must
+/// store it before the next attempt. Wrong guesses fail with `RS_STATUS_WRONG_SECRET`,
+/// then `RS_STATUS_THROTTLED`, `RS_STATUS_RECOVERY_REQUIRED` or `RS_STATUS_LOCKED_OUT`.
+///
+/// # Safety
+/// `blob`, `device_key` and (when not null) `record` must point to that many readable
+/// bytes, `secret` must be NUL-terminated UTF-8 and both out-pointers must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_unlock_guarded(
+    blob: *const u8,
+    blob_len: usize,
+    secret: *const c_char,
+    device_key: *const u8,
+    device_key_len: usize,
+    record: *const u8,
+    record_len: usize,
+    out_record: *mut RsBuffer,
+    out_vault: *mut *mut RsVault,
+) -> RsStatus {
+    guard(|| {
+        let out = unsafe { out_vault.as_mut() }.ok_or(RsStatus::InvalidArgument)?;
+        *out = std::ptr::null_mut();
+        let out_record = unsafe { out_buffer(out_record) }?;
+        if blob.is_null() || device_key.is_null() {
+            return Err(RsStatus::InvalidArgument);
+        }
+        let blob = unsafe { slice::from_raw_parts(blob, blob_len) };
+        let device_key = unsafe { slice::from_raw_parts(device_key, device_key_len) };
+        let secret = unsafe { c_str(secret) }?;
+
+        let policy = ThrottlePolicy::default();
+        let mut attempts = match record.is_null() {
+            true => AttemptRecord::new(blob)?,
+            false => match AttemptRecord::from_bytes(unsafe { slice::from_raw_parts(record, record_len) }, device_key, blob) {
+                Err(RichieSafeError::AttemptRecordTampered) => AttemptRecord::tripped(blob, &policy)?,
+                other => other?,
+            },
+        };
+        let unlocked = attempts::unlock(blob, secret, &mut attempts, &policy);
+        *out_record = RsBuffer::from_vec(attempts.to_bytes(device_key));
+
+        *out = Box::into_raw(Box::new(RsVault { inner: unlocked?.handle }));
+        Ok(())
+    })
+}
+
+/// Encrypts the current state under the vault's header and writes the blob to `out_blob`.
+///
+/// # Safety
+/// `vault` must come from `rs_vault_unlock`; `out_blob` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_seal(vault: *const RsVault, out_blob: *mut RsBuffer) -> RsStatus {
+    guard(|| {
+        let out = unsafe { out_buffer(out_blob) }?;
+        let vault = unsafe { vault_ref(vault) }?;
+        *out = RsBuffer::from_vec(vault.inner.seal()?);
+        Ok(())
+    })
+}
+
+/// Locks the vault: wipes the key and decrypted entries. Null is ignored.
+///
+/// # Safety
+/// `vault` must come from `rs_vault_unlock` and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_free(vault: *mut RsVault) {
+    if !vault.is_null() {
+        drop(unsafe { Box::from_raw(vault) });
+    }
+}
+
+/// Writes a JSON array of `{id, title, kind, tags, updated_at}` to `out_json`.
+///
+/// # Safety
+/// `vault` must come from `rs_vault_unlock`; `out_json` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_list_entries(vault: *const RsVault, out_json: *mut RsBuffer) -> RsStatus {
+    guard(|| {
+        let out = unsafe { out_buffer(out_json) }?;
+        let vault = unsafe { vault_ref(vault) }?;
+
+        let summaries: Vec<EntrySummary> = vault.inner.state.entries.iter()
+            .map(|e| EntrySummary {
+                id: e.id,
+                title: &e.title,
+                kind: e.kind.name(),
+                tags: &e.tags,
+                updated_at: e.updated_at,
+            })
+            .collect();
+        *out = to_json(&summaries)?;
+        Ok(())
+    })
+}
+
+/// Writes the full entry, secrets included, as JSON to `out_json`.
+///
+/// # Safety
+/// `vault` must come from `rs_vault_unlock`, `id` must be a NUL-terminated UUID
+/// string and `out_json` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_get_entry(
+    vault: *const RsVault,
+    id: *const c_char,
+    out_json: *mut RsBuffer,
+) -> RsStatus {
+    guard(|| {
+        let out = unsafe { out_buffer(out_json) }?;
+        let vault = unsafe { vault_ref(vault) }?;
+        let id = unsafe { c_id(id) }?;
+
+        *out = to_json(vault.inner.get_entry(&id)?)?;
+        Ok(())
+    })
+}
+
+/// Adds an entry described by `entry_json` (`{title, kind, notes, tags}`, with `kind`
+/// tagged by `type`) and writes its new UUID string to `out_id`. The change is in memory until `rs_vault_seal`.
+///
+/// # Safety
+/// `vault` must come from `rs_vault_unlock`, `entry_json` must be NUL-terminated
+/// UTF-8 and `out_id` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_add_entry(
+    vault: *mut RsVault,
+    entry_json: *const c_char,
+    out_id: *mut RsBuffer,
+) -> RsStatus {
+    guard(|| {
+        let out = unsafe { out_buffer(out_id) }?;
+        let vault = unsafe { vault_mut(vault) }?;
+        let input = parse_entry(unsafe { c_str(entry_json) }?)?;
+
+        let mut entry = VaultEntry::new(&input.title, input.kind);
+        entry.notes = input.notes;
+        entry.tags = input.tags;
+        entry.fields = validated(input.fields)?.unwrap_or_default();
+        let id = vault.inner.add_entry(entry);
+
+        *out = RsBuffer::from_vec(id.to_string().into_bytes());
+        Ok(())
+    })
+}
+
+/// Replaces the title, kind, notes, tags and (when given) custom fields of an
+/// entry. Attachments and `created_at` are kept.
+///
+/// # Safety
+/// `vault` must come from `rs_vault_unlock`; `id` and `entry_json` must be
+/// NUL-terminated UTF-8.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_update_entry(
+    vault: *mut RsVault,
+    id: *const c_char,
+    entry_json: *const c_char,
+) -> RsStatus {
+    guard(|| {
+        let vault = unsafe { vault_mut(vault) }?;
+        let id = unsafe { c_id(id) }?;
+        let input = parse_entry(unsafe { c_str(entry_json) }?)?;
+        let fields = validated(input.fields)?;
+
+        vault.inner.update_entry(&id, |e| {
+            e.title = input.title;
+            e.kind = input.kind;
+            e.notes = input.notes;
+            e.tags = input.tags;
+            if let Some(fields) = fields {
+                e.fields = fields;
+            }
+        })?;
+        Ok(())
+    })
+}
+
+/// Removes an entry.
+///
+/// # Safety
+/// `vault` must come from `rs_vault_unlock`; `id` must be a NUL-terminated UUID string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_vault_delete_entry(vault: *mut RsVault, id: *const c_char) -> RsStatus {
+    guard(|| {
+        let vault = unsafe { vault_mut(vault) }?;
+        let id = unsafe { c_id(id) }?;
+        vault.inner.delete_entry(&id)?;
+        Ok(())
+    })
+}
+
+
+/// Writes `{"entropy_bits":…,"score":0-4,"reasons":["sequence",…]}` for `secret`
+/// to `out_json`.
+///
+/// # Safety
+/// `secret` must be NUL-terminated UTF-8 and `out_json` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_estimate_strength(secret: *const c_char, out_json: *mut RsBuffer) -> RsStatus {
+    guard(|| {
+        let out = unsafe { out_buffer(out_json) }?;
+        let secret = unsafe { c_str(secret) }?;
+        *out = to_json(&strength::estimate(secret))?;
+        Ok(())
+    })
+}
+
+/// Sets the process-wide requirements for new PINs (`rs_vault_create` and PIN
+/// changes fail with `WeakSecret` below them). `0, 0` accepts anything.
+#[unsafe(no_mangle)]
+pub extern "C" fn rs_set_strength_policy(min_length: u32, min_score: u8) -> RsStatus {
+    guard(|| {
+        if min_score > 4 {
+            return Err(RsStatus::InvalidArgument);
+        }
+        strength::set_policy(StrengthPolicy { min_length: min_length as usize, min_score });
+        Ok(())
+    })
+}
@@ -0,0 +1,51 @@
+import com.richiesafe.core.*
+
+val fast = KdfConfig(mCost = 19456u, tCost = 2u, pCost = 1u)
+var blob = createVault(VaultKind.REAL, "1234", "alpha beta gamma", fast)
+
+// Wrong secrets surface as typed exceptions carrying the stable core code
+try {
+    unlockVault(blob, "0000")
+    throw AssertionError("wrong PIN must not unlock")
+} catch (e: VaultException.Core) {
+    assert(e.name == "WRONG_SECRET")
+    assert(e.code == 1)
+}
+
+unlockVault(blob, "1234").use { vault ->
+    val id = vault.addEntry(NewEntry(
+        title = "Mail",
+        content = EntryContent.Login(username = "alice", password = "pw", url = null),
+        notes = null,
+        tags = listOf("work"),
+    ))
+    assert(vault.listEntries().single().kind == "login")
+
+    vault.updateEntry(id, NewEntry(
+        title = "Mail",
+        content = EntryContent.Login(username = "alice", password = "new", url = null),
+        notes = "rotated",
+        tags = listOf(),
+    ))
+    val entry = vault.getEntry(id)
+    assert((entry.content as EntryContent.Login).password == "new")
+    assert(entry.notes == "rotated")
+
+    try {
+        vault.getEntry("not-a-uuid")
+        throw AssertionError("malformed ids must be rejected")
+    } catch (e: VaultException.InvalidArgument) {
+    }
+
+    blob = vault.changePin("1234", "5678", fast)
+    blob = vault.rotateRecovery("delta epsilon zeta", fast)
+}
+
+unlockVault(blob, "5678").use { vault ->
+    assert(vault.listEntries().size == 1)
+}
+unlockVault(blob, "delta epsilon zeta").use { vault ->
+    val id = vault.listEntries().single().id
+    vault.deleteEntry(id)
+    assert(vault.listEntries().isEmpty())
+}
@@ -0,0 +1,5 @@
+//! Runs the Kotlin scripts in tests/bindings against freshly generated bindings.
+//! `cargo test -p richiesafe-ffi --features kotlin-tests` (needs kotlinc and JNA).
+#![cfg(feature = "kotlin-tests")]
+
+uniffi::build_foreign_language_testcases!("tests/bindings/test_vault.kts");
@@ -1,17 +1,26 @@
+mod slots;
+
 use wasm_bindgen::prelude::*;
 use richiesafe_core::vault::{ops, header};
-use richiesafe_core::crypto::kdf::KdfParams;
-use richiesafe_core::models::entry::VaultEntry;
+use richiesafe_core::vault::attempts::{self, AttemptRecord, ThrottlePolicy};
+use richiesafe_core::crypto::kdf::{self, KdfParams};
+use richiesafe_core::models::entry::{AttachmentRef, CustomField, EntryKind, FieldType, LoginData, VaultEntry};
+use richiesafe_core::generator::{self, PassphraseOptions, PasswordOptions};
+use richiesafe_core::models::migration;
+use richiesafe_core::otp::{OtpKind, OtpSecret};
+use richiesafe_core::strength::{self, StrengthPolicy};
+use richiesafe_core::RichieSafeError;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use bip39::Mnemonic;
-use rand::{Rng, distributions::Alphanumeric};
-use serde::{Serialize};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize)]
 pub struct WasmVaultEntryMetadata {
     pub id: Uuid,
     pub title: String,
+    pub entry_type: &'static str,
     pub username: String,
     pub url: Option<String>,
     pub tags: Vec<String>,
@@ -19,6 +28,57 @@ pub struct WasmVaultEntryMetadata {
     pub updated_at: DateTime<Utc>,
     pub password_len: usize,
     pub has_notes: bool,
+    pub has_otp: bool,
+    pub fields: Vec<WasmFieldMetadata>,
+    pub attachments: Vec<AttachmentRef>,
+}
+
+/// A custom field as listed. `value` is `null` for hidden fields; fetch it with
+/// `get_entry_field_value`.
+#[derive(Serialize)]
+pub struct WasmFieldMetadata {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: &'static str,
+    pub value: Option<String>,
+}
+
+/// Element of the array passed to `set_entry_fields`.
+#[derive(Deserialize)]
+struct WasmFieldInput {
+    name: String,
+    #[serde(rename = "type")]
+    field_type: String,
+    value: String,
+}
+
+/// A freshly sealed attachment: the UI stores `blob` under `id`, outside the vault blob.
+#[wasm_bindgen]
+pub struct WasmAttachment {
+    id: Uuid,
+    blob: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl WasmAttachment {
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.id.to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn blob(&self) -> Vec<u8> {
+        self.blob.clone()
+    }
+}
+
+/// Converts a core error into a JS `Error` whose `name` is the stable code name
+/// and whose `code` property is the stable numeric code.
+fn to_js_error(e: RichieSafeError) -> JsValue {
+    let err = js_sys::Error::new(&e.to_string());
+    err.set_name(e.code_name());
+    let _ = js_sys::Reflect::set(&err, &JsValue::from_str("code"), &JsValue::from(e.code()));
+    err.into()
 }
 
 #[wasm_bindgen]
@@ -30,25 +90,48 @@ pub fn generate_mnemonic() -> String {
     mnemonic.to_string()
 }
 
-fn generate_random_password() -> String {
-    rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(16)
-        .map(char::from)
-        .collect()
+/// Browsers get a lower memory ceiling than native builds.
+const WASM_MAX_MEMORY_KIB: u32 = 64 * 1024;
+
+/// Header parameters accepted in the browser. The memory ceiling still admits
+/// vaults calibrated on desktop (`kdf::DEFAULT_MAX_MEMORY_KIB`) but stays well
+/// inside a 32-bit wasm heap.
+const WASM_KDF_POLICY: kdf::KdfPolicy = kdf::KdfPolicy {
+    max_m_cost: kdf::DEFAULT_MAX_MEMORY_KIB,
+    max_t_cost: 32,
+    max_p_cost: 4,
+    ..kdf::KdfPolicy::NATIVE
+};
+
+#[wasm_bindgen(start)]
+fn start() {
+    kdf::set_policy(WASM_KDF_POLICY);
+}
+
+/// Argon2id cost calibrated for this browser, timed with `Date.now()`.
+fn device_kdf_params() -> Result<KdfParams, JsValue> {
+    kdf::calibrate_with(kdf::DEFAULT_TARGET_MS, WASM_MAX_MEMORY_KIB, |params| {
+        let start = js_sys::Date::now();
+        kdf::derive_key(b"calibration", &[0u8; 16], params)?;
+        Ok(js_sys::Date::now() - start)
+    })
+    .map_err(to_js_error)
 }
 
 #[wasm_bindgen]
 pub struct WasmVaultHandle {
     inner: ops::VaultHandle, // Holds the zeroize-protected key
+    /// Attachments sealed by `add_entry` and `update_entry`, waiting for `take_sealed_attachments`
+    sealed_attachments: Vec<WasmAttachment>,
 }
 
 #[wasm_bindgen]
 impl WasmVaultHandle {
-    pub fn list_entries(&self) -> Result<JsValue, JsValue> {
-        // DEPRECATED: Use list_entries_metadata for security
-        serde_wasm_bindgen::to_value(&self.inner.state.entries)
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+    /// `"pin"` or `"recovery"`: which secret opened this vault, so the UI can
+    /// react to recovery-phrase unlocks.
+    #[wasm_bindgen(getter)]
+    pub fn unlocked_via(&self) -> String {
+        self.inner.unlocked_via().name().to_string()
     }
 
     pub fn list_entries_metadata(&self) -> Result<JsValue, JsValue> {
@@ -56,13 +139,21 @@ impl WasmVaultHandle {
             WasmVaultEntryMetadata {
                 id: e.id,
                 title: e.title.clone(),
-                username: e.username.clone(),
-                url: e.url.clone(),
+                entry_type: slots::entry_type(&e.kind),
+                username: slots::username(e),
+                url: slots::url(e),
                 tags: e.tags.clone(),
                 created_at: e.created_at,
                 updated_at: e.updated_at,
-                password_len: e.password.as_ref().map(|s| s.len()).unwrap_or(0),
-                has_notes: e.notes.as_ref().map(|s| !s.is_empty()).unwrap_or(false),
+                password_len: slots::secret(e).map(|s| s.len()).unwrap_or(0),
+                has_notes: slots::notes(e).map(|s| !s.is_empty()).unwrap_or(false),
+                has_otp: e.otp.is_some(),
+                fields: e.fields.iter().map(|f| WasmFieldMetadata {
+                    name: f.name.clone(),
+                    field_type: f.field_type.name(),
+                    value: (!f.is_hidden()).then(|| f.value.clone()),
+                }).collect(),
+                attachments: e.attachments.clone(),
             }
         }).collect();
         serde_wasm_bindgen::to_value(&meta)
@@ -71,8 +162,8 @@ impl WasmVaultHandle {
 
     pub fn get_entry_password(&self, id_str: &str) -> Result<Option<Box<[u8]>>, JsValue> {
         let id = Uuid::parse_str(id_str).map_err(|_| JsValue::from_str("Invalid ID"))?;
-        if let Some(entry) = self.inner.state.entries.iter().find(|e| e.id == id) {
-             Ok(entry.password.as_ref().map(|s| s.as_bytes().to_vec().into_boxed_slice()))
+        if let Some(entry) = self.inner.find_by_id(&id) {
+             Ok(slots::secret(entry).map(|s| s.into_bytes().into_boxed_slice()))
         } else {
              Ok(None)
         }
@@ -80,8 +171,8 @@ impl WasmVaultHandle {
 
     pub fn get_entry_notes(&self, id_str: &str) -> Result<Option<Box<[u8]>>, JsValue> {
         let id = Uuid::parse_str(id_str).map_err(|_| JsValue::from_str("Invalid ID"))?;
-        if let Some(entry) = self.inner.state.entries.iter().find(|e| e.id == id) {
-             Ok(entry.notes.as_ref().map(|s| s.as_bytes().to_vec().into_boxed_slice()))
+        if let Some(entry) = self.inner.find_by_id(&id) {
+             Ok(slots::notes(entry).map(|s| s.into_bytes().into_boxed_slice()))
         } else {
              Ok(None)
         }
@@ -89,83 +180,211 @@ impl WasmVaultHandle {
 
     pub fn add_entry(
         &mut self,
-        _type: &str, // "password", "card", "note", "image"
+        entry_type: &str, // "password", "card", "note", "image"
         title: &str,
         username: Option<String>,
         password: Option<String>,
         url: Option<String>,
         notes: Option<String>
-    ) -> Result<(), JsValue> {
+    ) -> Result<String, JsValue> {
         // Validation handled in UI, here we just insert
-        let new_entry = VaultEntry {
-            id: Uuid::new_v4(),
-            title: title.to_string(),
-            username: username.unwrap_or_default(),
-            password: password,
-            url: url,
-            notes: notes,
-            tags: vec![_type.to_string()], // Using tags to store type for MVP
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+        let (kind, notes) = migration::kind_from_legacy(entry_type, username.unwrap_or_default(), password, url, notes);
+        let mut new_entry = VaultEntry::new(title, kind);
+        new_entry.notes = notes;
+
+        // Images never stay inline: their bytes go into an attachment sealed under its own key
+        let image = match &mut new_entry.kind {
+            EntryKind::File(f) => Some((f.file_name.clone(), f.mime_type.clone(), std::mem::take(&mut f.data))),
+            _ => None,
+        };
+        let id = self.inner.add_entry(new_entry);
+        if let Some((file_name, mime_type, data)) = image {
+            match self.inner.add_attachment(&id, &file_name, mime_type, &data) {
+                Ok((reference, blob)) => self.sealed_attachments.push(WasmAttachment { id: reference.id, blob }),
+                Err(e) => {
+                    let _ = self.inner.delete_entry(&id);
+                    return Err(to_js_error(e));
+                }
+            }
+        }
+        Ok(id.to_string())
+    }
+
+    /// Attachment blobs sealed by `add_entry` and `update_entry` (image entries) since the last call.
+    /// The UI must store each `blob` under its `id` before persisting the vault.
+    pub fn take_sealed_attachments(&mut self) -> Vec<WasmAttachment> {
+        std::mem::take(&mut self.sealed_attachments)
+    }
+
+    /// Edits an entry in place, keeping its id and `created_at`.
+    /// `undefined` leaves a field untouched; an empty string clears an optional field.
+    /// A new image (data URL in `password`) is sealed into an attachment like in
+    /// `add_entry`; the returned ids are the attachments it replaced, whose stored
+    /// blobs the UI must delete.
+    pub fn update_entry(
+        &mut self,
+        id_str: &str,
+        title: Option<String>,
+        username: Option<String>,
+        mut password: Option<String>,
+        url: Option<String>,
+        notes: Option<String>
+    ) -> Result<Vec<String>, JsValue> {
+        let id = Uuid::parse_str(id_str)
+            .map_err(|_| JsValue::from_str("Invalid ID format"))?;
+
+        let image = match self.inner.find_by_id(&id).map(|e| &e.kind) {
+            Some(EntryKind::File(_)) => password.take().filter(|v| !v.is_empty()),
+            _ => None,
         };
+        let non_empty = |v: String| if v.is_empty() { None } else { Some(v) };
+        self.inner.update_entry(&id, |e| {
+            if let Some(v) = title { e.title = v; }
+            slots::apply(e, username, password.map(non_empty), url.map(non_empty), notes.map(non_empty));
+        }).map_err(to_js_error)?;
 
-        self.inner.state.entries.push(new_entry);
-        Ok(())
+        let Some(image) = image else { return Ok(Vec::new()) };
+        let (mime_type, data) = migration::decode_data_url(&image);
+        let file_name = match &self.inner.get_entry(&id).map_err(to_js_error)?.kind {
+            EntryKind::File(f) => f.file_name.clone(),
+            _ => String::new(),
+        };
+        let (reference, blob, dropped) = self.inner
+            .replace_attachments(&id, &file_name, mime_type, &data)
+            .map_err(to_js_error)?;
+        self.sealed_attachments.push(WasmAttachment { id: reference.id, blob });
+        Ok(dropped.iter().map(Uuid::to_string).collect())
     }
 
     pub fn delete_entry(&mut self, id_str: &str) -> Result<(), JsValue> {
         let target_id = Uuid::parse_str(id_str)
             .map_err(|_| JsValue::from_str("Invalid ID format"))?;
 
-        if let Some(pos) = self.inner.state.entries.iter().position(|e| e.id == target_id) {
-            self.inner.state.entries.remove(pos);
-            Ok(())
-        } else {
-            Err(JsValue::from_str("Entry not found"))
+        self.inner.delete_entry(&target_id).map_err(to_js_error)
+    }
+
+    /// Replaces the entry's custom fields with `fields`, an ordered array of
+    /// `{ name, type, value }` where `type` is `text`, `hidden`, `url`, `email`,
+    /// `date` or `boolean`.
+    pub fn set_entry_fields(&mut self, id_str: &str, fields: JsValue) -> Result<(), JsValue> {
+        let id = Uuid::parse_str(id_str)
+            .map_err(|_| JsValue::from_str("Invalid ID format"))?;
+        let inputs: Vec<WasmFieldInput> = serde_wasm_bindgen::from_value(fields)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        let mut fields = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let field_type = FieldType::from_name(&input.field_type)
+                .ok_or_else(|| JsValue::from_str("Unknown field type"))?;
+            fields.push(CustomField::new(&input.name, field_type, input.value).map_err(to_js_error)?);
         }
+        self.inner.update_entry(&id, |e| e.fields = fields).map_err(to_js_error)
+    }
+
+    /// Value of the custom field at `index`, including hidden ones, as UTF-8 bytes
+    /// the caller can wipe (same contract as `get_entry_password`).
+    pub fn get_entry_field_value(&self, id_str: &str, index: usize) -> Result<Option<Box<[u8]>>, JsValue> {
+        let id = Uuid::parse_str(id_str).map_err(|_| JsValue::from_str("Invalid ID"))?;
+        Ok(self.inner.find_by_id(&id)
+            .and_then(|e| e.fields.get(index))
+            .map(|f| f.value.clone().into_bytes().into_boxed_slice()))
+    }
+
+    /// Stores the seed of an `otpauth://` URI on an entry; `undefined` removes it.
+    pub fn set_entry_otp(&mut self, id_str: &str, uri: Option<String>) -> Result<(), JsValue> {
+        let id = Uuid::parse_str(id_str)
+            .map_err(|_| JsValue::from_str("Invalid ID format"))?;
+        let otp = uri.map(|uri| OtpSecret::from_uri(&uri)).transpose().map_err(to_js_error)?;
+        self.inner.update_entry(&id, |e| e.otp = otp).map_err(to_js_error)
+    }
+
+    /// `{ code, expires_in }` for the entry's TOTP seed at the browser's current time.
+    /// HOTP entries fail with `INVALID_OTP`; use `next_hotp_code`.
+    pub fn get_entry_totp(&self, id_str: &str) -> Result<JsValue, JsValue> {
+        let id = Uuid::parse_str(id_str)
+            .map_err(|_| JsValue::from_str("Invalid ID format"))?;
+        let entry = self.inner.get_entry(&id).map_err(to_js_error)?;
+        if matches!(entry.otp.as_ref().map(|o| &o.kind), Some(OtpKind::Hotp { .. })) {
+            return Err(to_js_error(RichieSafeError::InvalidOtp("HOTP seeds advance with next_hotp_code".to_string())));
+        }
+        let now = (js_sys::Date::now() / 1000.0) as u64;
+        let code = self.inner.entry_otp_code(&id, now).map_err(to_js_error)?;
+        serde_wasm_bindgen::to_value(&code)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// `{ code, expires_in: null }` for the entry's HOTP counter, which moves on by one.
+    /// The new counter is only kept once the caller stores `export()`.
+    pub fn next_hotp_code(&mut self, id_str: &str) -> Result<JsValue, JsValue> {
+        let id = Uuid::parse_str(id_str)
+            .map_err(|_| JsValue::from_str("Invalid ID format"))?;
+        let code = self.inner.next_hotp_code(&id).map_err(to_js_error)?;
+        serde_wasm_bindgen::to_value(&code)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn add_attachment(
+        &mut self,
+        entry_id: &str,
+        file_name: &str,
+        mime_type: Option<String>,
+        data: &[u8]
+    ) -> Result<WasmAttachment, JsValue> {
+        let entry_id = Uuid::parse_str(entry_id)
+            .map_err(|_| JsValue::from_str("Invalid ID format"))?;
+        let (reference, blob) = self.inner.add_attachment(&entry_id, file_name, mime_type, data)
+            .map_err(to_js_error)?;
+        Ok(WasmAttachment { id: reference.id, blob })
+    }
+
+    pub fn open_attachment(&self, attachment_id: &str, blob: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let attachment_id = Uuid::parse_str(attachment_id)
+            .map_err(|_| JsValue::from_str("Invalid ID format"))?;
+        self.inner.open_attachment(&attachment_id, blob).map_err(to_js_error)
+    }
+
+    pub fn remove_attachment(&mut self, entry_id: &str, attachment_id: &str) -> Result<(), JsValue> {
+        let entry_id = Uuid::parse_str(entry_id)
+            .map_err(|_| JsValue::from_str("Invalid ID format"))?;
+        let attachment_id = Uuid::parse_str(attachment_id)
+            .map_err(|_| JsValue::from_str("Invalid ID format"))?;
+        self.inner.remove_attachment(&entry_id, &attachment_id).map_err(to_js_error)
+    }
+
+    /// Moves inline images out of the vault body. Store every returned attachment
+    /// before persisting `export()`.
+    pub fn externalize_files(&mut self) -> Result<Vec<WasmAttachment>, JsValue> {
+        let blobs = self.inner.externalize_files().map_err(to_js_error)?;
+        Ok(blobs.into_iter().map(|(id, blob)| WasmAttachment { id, blob }).collect())
     }
 
     #[wasm_bindgen]
-    pub fn change_pin(&mut self, _old_pin: &str, new_pin: &str) -> Result<(), JsValue> {
-        let params = KdfParams { m_cost: 19456, t_cost: 2, p_cost: 1 };
-        
+    pub fn change_pin(&mut self, old_pin: &str, new_pin: &str) -> Result<(), JsValue> {
+        let params = device_kdf_params()?;
+
         // This updates the inner handle's header AND returns the new blob (which we ignore here,
         // because export() will regenerate it correctly now that the header is updated)
-        let _ = ops::change_pin(&mut self.inner, new_pin, params)
-            .map_err(|e| JsValue::from_str(&e))?;
+        let _ = ops::change_pin(&mut self.inner, old_pin, new_pin, params)
+            .map_err(to_js_error)?;
             
         Ok(())
     }
     
-    // For MVP we don't implement full save logic (re-encryption) because we don't have the blob store.
-    // The handle is in-memory.
-    // NOTE: In a real app we'd re-encrypt and return the blob to save.
-    // I will add a method to `export_blob` so the UI can save it to localStorage.
-    
+    /// Replaces the recovery phrase with a freshly generated one and returns it so the UI
+    /// can show it once. The PIN keeps working; call `export()` to persist.
+    pub fn rotate_recovery(&mut self) -> Result<String, JsValue> {
+        let params = device_kdf_params()?;
+        let phrase = generate_mnemonic();
+
+        let _ = ops::rotate_recovery(&mut self.inner, &phrase, params)
+            .map_err(to_js_error)?;
+
+        Ok(phrase)
+    }
+
+    /// Seals the current state into a blob the UI can persist.
     pub fn export(&self) -> Result<Vec<u8>, JsValue> {
-       // We need to re-encrypt state.
-       // ops::encrypt_vault_state(&self.inner) -- we need to expose this or impl it here.
-       // Re-implementing simplified encryption here using the key in handle.
-       
-       use richiesafe_core::crypto::{rng, aead};
-       use richiesafe_core::vault::format;
-       use std::convert::TryInto;
-       
-       let state_bytes = serde_cbor::to_vec(&self.inner.state)
-           .map_err(|e| JsValue::from_str(&e.to_string()))?;
-           
-       let body_nonce_vec = rng::generate_bytes(24);
-       let body_nonce: [u8;24] = body_nonce_vec.clone().try_into().unwrap();
-       let header_bytes = self.inner.original_header.to_bytes();
-       
-       let ciphertext = aead::encrypt(
-           &self.inner.vault_key,
-           &body_nonce,
-           &state_bytes,
-           &header_bytes
-       ).map_err(|e| JsValue::from_str(&e))?;
-       
-       Ok(format::assemble(&self.inner.original_header, &body_nonce, &ciphertext))
+        self.inner.seal().map_err(to_js_error)
     }
     
     pub fn lock(self) {
@@ -194,24 +413,18 @@ impl VaultPair {
 
 #[wasm_bindgen]
 pub fn create_vault_pair(pin_real: &str, pin_panic: &str, recovery: &str) -> Result<VaultPair, JsValue> {
-    let params = KdfParams {
-        m_cost: 32 * 1024,
-        t_cost: 3,
-        p_cost: 1,
-    };
+    let params = device_kdf_params()?;
 
     let real_blob = ops::create_vault(header::VaultType::Real, pin_real, recovery, params, params)
-        .map_err(|e| JsValue::from_str(&e))?;
+        .map_err(to_js_error)?;
         
     let mut decoy_blob = ops::create_vault(header::VaultType::Decoy, pin_panic, recovery, params, params)
-        .map_err(|e| JsValue::from_str(&e))?;
+        .map_err(to_js_error)?;
 
-    // --- DECOY POPULATION START ---
-    // Automatically unlock the decoy vault to add fake entries
     // --- DECOY POPULATION START ---
     // Automatically unlock the decoy vault to add fake entries
     let mut decoy_handle = ops::unlock_vault(&decoy_blob, pin_panic)
-        .map_err(|e| JsValue::from_str(&e))?;
+        .map_err(to_js_error)?;
     
     let fakes = vec![
         ("Netflix", "user@example.com", "https://netflix.com"),
@@ -222,45 +435,19 @@ pub fn create_vault_pair(pin_real: &str, pin_panic: &str, recovery: &str) -> Res
     ];
 
     for (title, user, url) in fakes {
-         let pass = generate_random_password();
-         let new_entry = VaultEntry {
-            id: Uuid::new_v4(),
-            title: title.to_string(),
+         let pass = generator::generate_password(&decoy_password_options())
+             .map_err(to_js_error)?
+             .to_string();
+         let new_entry = VaultEntry::new(title, EntryKind::Login(LoginData {
             username: user.to_string(),
             password: Some(pass),
             url: Some(url.to_string()),
-            notes: None,
-            tags: vec!["password".to_string()],
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        };
-        decoy_handle.state.entries.push(new_entry);
+        }));
+        decoy_handle.add_entry(new_entry);
     }
     
     // Re-encrypt the populated decoy vault
-    // We need to replicate the export logic here because we don't have a WasmVaultHandle wrapper handy
-    // or we can just reconstruct the WasmVaultHandle and call export? No, `export` is a method on the struct.
-    // Let's just use the ops logic directly to be safe and clean.
-    
-    use richiesafe_core::crypto::{rng, aead};
-    use richiesafe_core::vault::format;
-    use std::convert::TryInto;
-       
-    let state_bytes = serde_cbor::to_vec(&decoy_handle.state)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-           
-    let body_nonce_vec = rng::generate_bytes(24);
-    let body_nonce: [u8;24] = body_nonce_vec.clone().try_into().unwrap();
-    let header_bytes = decoy_handle.original_header.to_bytes();
-       
-    let ciphertext = aead::encrypt(
-        &decoy_handle.vault_key,
-        &body_nonce,
-        &state_bytes,
-        &header_bytes
-    ).map_err(|e| JsValue::from_str(&e))?;
-       
-    decoy_blob = format::assemble(&decoy_handle.original_header, &body_nonce, &ciphertext);
+    decoy_blob = decoy_handle.seal().map_err(to_js_error)?;
     // --- DECOY POPULATION END ---
         
     Ok(VaultPair {
@@ -269,10 +456,106 @@ pub fn create_vault_pair(pin_real: &str, pin_panic: &str, recovery: &str) -> Res
     })
 }
 
+/// Unthrottled unlock, for a blob the user just created. Unlocks from the lock
+/// screen go through `WasmAttemptGuard` instead.
 #[wasm_bindgen]
 pub fn unlock_vault(blob: &[u8], secret: &str) -> Result<WasmVaultHandle, JsValue> {
     let handle = ops::unlock_vault(blob, secret)
-        .map_err(|e| JsValue::from_str(&e))?;
+        .map_err(to_js_error)?;
         
-    Ok(WasmVaultHandle { inner: handle })
+    Ok(WasmVaultHandle { inner: handle, sealed_attachments: Vec::new() })
+}
+
+/// Failed-unlock throttling for one vault. The host keeps `record` next to the
+/// vault blob and writes it back after every `unlock` call, whether it succeeded
+/// or not. `device_key` should come from storage the page script cannot export
+/// (e.g. a non-extractable key); see `vault::attempts` for what the record can and
+/// cannot detect.
+#[wasm_bindgen]
+pub struct WasmAttemptGuard {
+    record: AttemptRecord,
+    device_key: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl WasmAttemptGuard {
+    /// `stored` is the last saved `record`, or nothing for a vault never unlocked
+    /// here. A stored record that fails to verify locks as if the limit was reached.
+    #[wasm_bindgen(constructor)]
+    pub fn new(blob: &[u8], device_key: Vec<u8>, stored: Option<Vec<u8>>) -> Result<WasmAttemptGuard, JsValue> {
+        let policy = ThrottlePolicy::default();
+        let record = match stored {
+            Some(bytes) => match AttemptRecord::from_bytes(&bytes, &device_key, blob) {
+                Err(RichieSafeError::AttemptRecordTampered) => AttemptRecord::tripped(blob, &policy),
+                other => other,
+            },
+            None => AttemptRecord::new(blob),
+        }
+        .map_err(to_js_error)?;
+        Ok(WasmAttemptGuard { record, device_key })
+    }
+
+    /// Fails with `THROTTLED`, `RECOVERY_REQUIRED` or `LOCKED_OUT` once too many
+    /// wrong secrets were tried.
+    pub fn unlock(&mut self, blob: &[u8], secret: &str) -> Result<WasmVaultHandle, JsValue> {
+        let unlocked = attempts::unlock(blob, secret, &mut self.record, &ThrottlePolicy::default())
+            .map_err(to_js_error)?;
+        Ok(WasmVaultHandle { inner: unlocked.handle, sealed_attachments: Vec::new() })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn failures(&self) -> u32 {
+        self.record.failures()
+    }
+
+    /// Serialized record to persist.
+    #[wasm_bindgen(getter)]
+    pub fn record(&self) -> Vec<u8> {
+        self.record.to_bytes(&self.device_key)
+    }
+}
+
+/// `{ entropy_bits, score, reasons }` for a PIN or password, e.g. to drive a meter.
+#[wasm_bindgen]
+pub fn estimate_strength(secret: &str) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&strength::estimate(secret))
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Requirements for PINs passed to `create_vault_pair` and `change_pin`. `0, 0` accepts
+/// anything. `min_score` is 0-4, as in the ffi binding.
+#[wasm_bindgen]
+pub fn set_strength_policy(min_length: usize, min_score: u8) -> Result<(), JsValue> {
+    if min_score > 4 {
+        return Err(JsValue::from_str("min_score is 0-4"));
+    }
+    strength::set_policy(StrengthPolicy { min_length, min_score });
+    Ok(())
+}
+
+/// Decoy logins look like what a person would have generated: 16 alphanumerics.
+fn decoy_password_options() -> PasswordOptions {
+    PasswordOptions { length: 16, symbols: false, require_each_class: false, ..Default::default() }
+}
+
+/// Random password. `options` is a (possibly partial) `PasswordOptions` object;
+/// `undefined` uses the defaults (20 characters, all classes).
+#[wasm_bindgen]
+pub fn generate_password(options: JsValue) -> Result<String, JsValue> {
+    let options: PasswordOptions = match options.is_undefined() {
+        true => PasswordOptions::default(),
+        false => serde_wasm_bindgen::from_value(options).map_err(|e| JsValue::from_str(&e.to_string()))?,
+    };
+    Ok(generator::generate_password(&options).map_err(to_js_error)?.to_string())
+}
+
+/// Random BIP39-word passphrase. `options` is a (possibly partial) `PassphraseOptions`
+/// object; `undefined` gives six words joined by `-`.
+#[wasm_bindgen]
+pub fn generate_passphrase(options: JsValue) -> Result<String, JsValue> {
+    let options: PassphraseOptions = match options.is_undefined() {
+        true => PassphraseOptions::default(),
+        false => serde_wasm_bindgen::from_value(options).map_err(|e| JsValue::from_str(&e.to_string()))?,
+    };
+    Ok(generator::generate_passphrase(&options).map_err(to_js_error)?.to_string())
 }
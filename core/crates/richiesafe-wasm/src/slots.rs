@@ -0,0 +1,96 @@
+//! The web UI still reads and writes every entry through the v1 slots
+//! (type, username, password, url, notes). These helpers map typed
+//! `EntryKind`s onto those slots and back.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use richiesafe_core::models::entry::{EntryKind, VaultEntry};
+
+/// UI type name ("password", "card", "note", "image", "identity").
+pub fn entry_type(kind: &EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Login(_) => "password",
+        EntryKind::Card(_) => "card",
+        EntryKind::Note(_) => "note",
+        EntryKind::File(_) => "image",
+        EntryKind::Identity(_) => "identity",
+    }
+}
+
+pub fn username(entry: &VaultEntry) -> String {
+    match &entry.kind {
+        EntryKind::Login(l) => l.username.clone(),
+        EntryKind::Card(c) => c.number.clone(),
+        EntryKind::Note(_) => String::new(),
+        EntryKind::File(f) => f.file_name.clone(),
+        EntryKind::Identity(i) => i.full_name.clone(),
+    }
+}
+
+pub fn url(entry: &VaultEntry) -> Option<String> {
+    match &entry.kind {
+        EntryKind::Login(l) => l.url.clone(),
+        _ => None,
+    }
+}
+
+/// Value of the UI "password" slot; files come back as a data URL.
+pub fn secret(entry: &VaultEntry) -> Option<String> {
+    match &entry.kind {
+        EntryKind::Login(l) => l.password.clone(),
+        EntryKind::Card(c) => c.cvv.clone(),
+        EntryKind::Note(n) => Some(n.text.clone()),
+        EntryKind::File(f) => Some(format!(
+            "data:{};base64,{}",
+            f.mime_type.as_deref().unwrap_or("application/octet-stream"),
+            BASE64.encode(&f.data)
+        )),
+        EntryKind::Identity(i) => i.document_number.clone(),
+    }
+}
+
+/// Value of the UI "notes" slot; cards keep their expiry there.
+pub fn notes(entry: &VaultEntry) -> Option<String> {
+    match &entry.kind {
+        EntryKind::Card(c) => c.expiry.clone(),
+        _ => entry.notes.clone(),
+    }
+}
+
+/// Writes UI slots back into the typed entry. `None` leaves a slot untouched.
+pub fn apply(
+    entry: &mut VaultEntry,
+    username: Option<String>,
+    password: Option<Option<String>>,
+    url: Option<Option<String>>,
+    notes: Option<Option<String>>,
+) {
+    let mut entry_notes = notes.clone();
+    match &mut entry.kind {
+        EntryKind::Login(l) => {
+            if let Some(v) = username { l.username = v; }
+            if let Some(v) = password { l.password = v; }
+            if let Some(v) = url { l.url = v; }
+        }
+        EntryKind::Card(c) => {
+            if let Some(v) = username { c.number = v; }
+            if let Some(v) = password { c.cvv = v; }
+            if let Some(v) = notes { c.expiry = v; }
+            entry_notes = None;
+        }
+        EntryKind::Note(n) => {
+            if let Some(v) = password { n.text = v.unwrap_or_default(); }
+        }
+        // New file content goes through `VaultHandle::replace_attachments`, never inline
+        EntryKind::File(f) => {
+            if let Some(v) = username { f.file_name = v; }
+        }
+        EntryKind::Identity(i) => {
+            if let Some(v) = username { i.full_name = v; }
+            if let Some(v) = password { i.document_number = v; }
+        }
+    }
+    if let Some(v) = entry_notes {
+        entry.notes = v;
+    }
+}
@@ -0,0 +1,55 @@
+//! `#[tauri::command]` wrappers around `DesktopVault`. KDF-heavy commands are
+//! async so they run off the main thread.
+
+use crate::vault::{DesktopVault, EntryInput, EntrySummary, Result};
+use tauri::State;
+
+#[tauri::command]
+pub fn vault_exists(vault: State<'_, DesktopVault>) -> bool {
+    vault.exists()
+}
+
+#[tauri::command]
+pub async fn create_vault(vault: State<'_, DesktopVault>, pin: String, recovery: String) -> Result<()> {
+    vault.create(&pin, &recovery)
+}
+
+#[tauri::command]
+pub async fn unlock_vault(vault: State<'_, DesktopVault>, secret: String) -> Result<()> {
+    vault.unlock(&secret)
+}
+
+#[tauri::command]
+pub fn lock_vault(vault: State<'_, DesktopVault>) {
+    vault.lock()
+}
+
+#[tauri::command]
+pub fn list_entries(vault: State<'_, DesktopVault>) -> Result<Vec<EntrySummary>> {
+    vault.list()
+}
+
+#[tauri::command]
+pub fn get_entry_field(vault: State<'_, DesktopVault>, id: String, field: String) -> Result<Option<String>> {
+    vault.field(&id, &field)
+}
+
+#[tauri::command]
+pub fn add_entry(vault: State<'_, DesktopVault>, entry: EntryInput) -> Result<String> {
+    vault.add(entry).map(|id| id.to_string())
+}
+
+#[tauri::command]
+pub fn update_entry(vault: State<'_, DesktopVault>, id: String, entry: EntryInput) -> Result<()> {
+    vault.update(&id, entry)
+}
+
+#[tauri::command]
+pub fn delete_entry(vault: State<'_, DesktopVault>, id: String) -> Result<()> {
+    vault.delete(&id)
+}
+
+#[tauri::command]
+pub fn export_vault(vault: State<'_, DesktopVault>) -> Result<Vec<u8>> {
+    vault.export()
+}
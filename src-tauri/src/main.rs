@@ -1,8 +1,32 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod commands;
+mod vault;
+
+use tauri::Manager;
+use vault::DesktopVault;
+
 fn main() {
     tauri::Builder::default()
+        .setup(|app| {
+            let data_dir = app.path_resolver().app_data_dir()
+                .expect("no app data directory on this platform");
+            app.manage(DesktopVault::new(data_dir.join("vault.rsafe")));
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            commands::vault_exists,
+            commands::create_vault,
+            commands::unlock_vault,
+            commands::lock_vault,
+            commands::list_entries,
+            commands::get_entry_field,
+            commands::add_entry,
+            commands::update_entry,
+            commands::delete_entry,
+            commands::export_vault,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,279 @@
+//! Vault state held on the Rust side of the desktop app. The decrypted
+//! `VaultHandle` never leaves this module; commands only hand the webview
+//! entry metadata or the single field it asked for.
+
+use chrono::{DateTime, Utc};
+use richiesafe_core::crypto::{kdf, rng};
+use richiesafe_core::models::entry::{CustomField, EntryKind, VaultEntry};
+use richiesafe_core::storage::{self, VaultStore};
+use richiesafe_core::vault::attempts::ThrottlePolicy;
+use richiesafe_core::vault::header::{UnlockMethodType, VaultType};
+use richiesafe_core::vault::ops::{self, VaultHandle};
+use richiesafe_core::RichieSafeError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// Error shape returned to JS, matching the `name`/`code` the WASM build puts on its errors.
+#[derive(Debug, Serialize)]
+pub struct CommandError {
+    pub name: String,
+    /// `RichieSafeError::code()` for core errors, absent for desktop-only ones
+    pub code: Option<u32>,
+    pub message: String,
+}
+
+impl CommandError {
+    fn new(name: &str, message: impl Into<String>) -> Self {
+        CommandError { name: name.to_string(), code: None, message: message.into() }
+    }
+
+    fn locked() -> Self {
+        CommandError::new("VAULT_LOCKED", "vault is locked")
+    }
+
+    fn invalid(message: impl Into<String>) -> Self {
+        CommandError::new("INVALID_ARGUMENT", message)
+    }
+}
+
+impl From<RichieSafeError> for CommandError {
+    fn from(e: RichieSafeError) -> Self {
+        CommandError { name: e.code_name().to_string(), code: Some(e.code()), message: e.to_string() }
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(e: io::Error) -> Self {
+        CommandError::new("IO", e.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, CommandError>;
+
+/// Payload of `add_entry` / `update_entry`. `kind` uses the vault's tagged layout,
+/// e.g. `{ "type": "login", "username": "alice", "password": "pw", "url": null }`.
+#[derive(Deserialize)]
+pub struct EntryInput {
+    pub title: String,
+    pub kind: EntryKind,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Custom fields; omitted leaves an existing entry's fields untouched
+    #[serde(default)]
+    pub fields: Option<Vec<CustomField>>,
+}
+
+/// What `list_entries` exposes: nothing secret.
+#[derive(Serialize)]
+pub struct EntrySummary {
+    pub id: Uuid,
+    pub title: String,
+    pub kind: &'static str,
+    pub tags: Vec<String>,
+    pub attachments: usize,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Timestamped copies of the vault file kept next to it
+const BACKUPS: usize = 5;
+const DEVICE_KEY_SIZE: usize = 32;
+
+pub struct DesktopVault {
+    store: VaultStore,
+    /// Signs the failed-attempt record; lives beside the vault in the app data dir
+    device_key: PathBuf,
+    handle: Mutex<Option<VaultHandle>>,
+}
+
+impl DesktopVault {
+    pub fn new(path: PathBuf) -> Self {
+        let device_key = path.with_file_name("device.key");
+        DesktopVault { store: VaultStore::new(path, BACKUPS), device_key, handle: Mutex::new(None) }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.store.exists()
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, Option<VaultHandle>> {
+        self.handle.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn with_handle<T>(&self, f: impl FnOnce(&VaultHandle) -> Result<T>) -> Result<T> {
+        f(self.lock_state().as_ref().ok_or_else(CommandError::locked)?)
+    }
+
+    /// Runs an edit and persists the resealed vault before returning.
+    fn with_handle_mut<T>(&self, f: impl FnOnce(&mut VaultHandle) -> Result<T>) -> Result<T> {
+        let mut state = self.lock_state();
+        let handle = state.as_mut().ok_or_else(CommandError::locked)?;
+        let out = f(handle)?;
+        self.store.save(&handle.seal()?)?;
+        Ok(out)
+    }
+
+    /// Creates the vault file and leaves it unlocked.
+    pub fn create(&self, pin: &str, recovery: &str) -> Result<()> {
+        if self.exists() {
+            return Err(CommandError::new("VAULT_EXISTS", "a vault already exists"));
+        }
+        let params = kdf::calibrate(kdf::DEFAULT_TARGET_MS, kdf::DEFAULT_MAX_MEMORY_KIB)?;
+        let blob = ops::create_vault(VaultType::Real, pin, recovery, params, params)?;
+        if let Some(dir) = self.store.path().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        self.store.save(&blob)?;
+        // A record left by an earlier vault at this path would read as tampered and lock this one
+        match fs::remove_file(self.store.attempts_path()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+
+        // Only the PIN method needs deriving; the recovery one was just created
+        *self.lock_state() = Some(ops::unlock_with_method(&blob, UnlockMethodType::Pin, pin)?);
+        Ok(())
+    }
+
+    /// Throttled by the attempt record; falls back to the newest backup if the
+    /// vault file is damaged.
+    pub fn unlock(&self, secret: &str) -> Result<()> {
+        let device_key = load_device_key(&self.device_key)?;
+        let opened = self.store.open(secret, &device_key, &ThrottlePolicy::default())?;
+        *self.lock_state() = Some(opened.handle);
+        Ok(())
+    }
+
+    /// Drops the handle, which wipes the key and the decrypted entries.
+    pub fn lock(&self) {
+        self.lock_state().take();
+    }
+
+    pub fn list(&self) -> Result<Vec<EntrySummary>> {
+        self.with_handle(|h| {
+            Ok(h.state.entries.iter()
+                .map(|e| EntrySummary {
+                    id: e.id,
+                    title: e.title.clone(),
+                    kind: e.kind.name(),
+                    tags: e.tags.clone(),
+                    attachments: e.attachments.len(),
+                    created_at: e.created_at,
+                    updated_at: e.updated_at,
+                })
+                .collect())
+        })
+    }
+
+    /// Returns one field of one entry, e.g. `("…", "password")`.
+    pub fn field(&self, id: &str, field: &str) -> Result<Option<String>> {
+        let id = parse_id(id)?;
+        self.with_handle(|h| entry_field(h.get_entry(&id)?, field))
+    }
+
+    pub fn add(&self, input: EntryInput) -> Result<Uuid> {
+        let fields = validated(input.fields)?;
+        self.with_handle_mut(|h| {
+            let mut entry = VaultEntry::new(&input.title, input.kind);
+            entry.notes = input.notes;
+            entry.tags = input.tags;
+            entry.fields = fields.unwrap_or_default();
+            Ok(h.add_entry(entry))
+        })
+    }
+
+    /// Replaces title, kind, notes, tags and, when given, custom fields.
+    /// Attachments and `created_at` are kept.
+    pub fn update(&self, id: &str, input: EntryInput) -> Result<()> {
+        let id = parse_id(id)?;
+        let fields = validated(input.fields)?;
+        self.with_handle_mut(|h| {
+            h.update_entry(&id, |e| {
+                e.title = input.title;
+                e.kind = input.kind;
+                e.notes = input.notes;
+                e.tags = input.tags;
+                if let Some(fields) = fields {
+                    e.fields = fields;
+                }
+            })?;
+            Ok(())
+        })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        let id = parse_id(id)?;
+        self.with_handle_mut(|h| Ok(h.delete_entry(&id)?))
+    }
+
+    /// Sealed blob of the current state, for backups and sync.
+    pub fn export(&self) -> Result<Vec<u8>> {
+        self.with_handle(|h| Ok(h.seal()?))
+    }
+}
+
+/// Reads the device key, generating it the first time.
+fn load_device_key(path: &Path) -> Result<Vec<u8>> {
+    match fs::read(path) {
+        Ok(key) if key.len() == DEVICE_KEY_SIZE => return Ok(key),
+        Ok(_) => return Err(CommandError::new("IO", "device key file is invalid")),
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        Err(_) => {}
+    }
+    let key = rng::generate_bytes(DEVICE_KEY_SIZE);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    storage::write_private(path, &key)?;
+    Ok(key)
+}
+
+fn parse_id(id: &str) -> Result<Uuid> {
+    Uuid::parse_str(id).map_err(|_| CommandError::invalid("invalid entry id"))
+}
+
+fn validated(fields: Option<Vec<CustomField>>) -> Result<Option<Vec<CustomField>>> {
+    if let Some(fields) = &fields {
+        for field in fields {
+            field.validate()?;
+        }
+    }
+    Ok(fields)
+}
+
+/// Built-in fields first, then custom fields by name.
+fn entry_field(entry: &VaultEntry, field: &str) -> Result<Option<String>> {
+    let value = match (&entry.kind, field) {
+        (_, "title") => Some(entry.title.clone()),
+        (_, "notes") => entry.notes.clone(),
+        (EntryKind::Login(l), "username") => Some(l.username.clone()),
+        (EntryKind::Login(l), "password") => l.password.clone(),
+        (EntryKind::Login(l), "url") => l.url.clone(),
+        (EntryKind::Card(c), "holder") => c.holder.clone(),
+        (EntryKind::Card(c), "number") => Some(c.number.clone()),
+        (EntryKind::Card(c), "expiry") => c.expiry.clone(),
+        (EntryKind::Card(c), "cvv") => c.cvv.clone(),
+        (EntryKind::Note(n), "text") => Some(n.text.clone()),
+        (EntryKind::Identity(i), "full_name") => Some(i.full_name.clone()),
+        (EntryKind::Identity(i), "email") => i.email.clone(),
+        (EntryKind::Identity(i), "phone") => i.phone.clone(),
+        (EntryKind::Identity(i), "address") => i.address.clone(),
+        (EntryKind::Identity(i), "birth_date") => i.birth_date.clone(),
+        (EntryKind::Identity(i), "document_number") => i.document_number.clone(),
+        (EntryKind::File(f), "file_name") => Some(f.file_name.clone()),
+        (EntryKind::File(f), "mime_type") => f.mime_type.clone(),
+        (kind, _) => match entry.fields.iter().find(|f| f.name == field) {
+            Some(custom) => Some(custom.value.clone()),
+            None => {
+                return Err(CommandError::invalid(format!("{} entries have no field '{field}'", kind.name())));
+            }
+        },
+    };
+    Ok(value)
+}
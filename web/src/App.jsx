@@ -535,7 +535,11 @@ const AuthScreen = ({ isDarkMode, setIsDarkMode, user }) => {
 
       clearSensitiveInputs();
     } catch (e) {
-      setError(String(e).includes("Biometria") ? String(e.message) : "PIN incorreto.");
+      if (e?.name === "THROTTLED" || e?.name === "LOCKED_OUT" || e?.name === "RECOVERY_REQUIRED") {
+        setError(`Demasiadas tentativas falhadas. ${e.message}`);
+      } else {
+        setError(String(e).includes("Biometria") ? String(e.message) : "PIN incorreto.");
+      }
       console.error(e);
     } finally {
       setLoading(false);
@@ -1495,18 +1499,19 @@ const MainApp = ({ isDarkMode, setIsDarkMode, onLogout, user, onConnect }) => {
 
           let strength = "Seguro";
           if (diffDays > 90) strength = "Crítico";
-          else if ((i.tags?.[0] || "password") === "password" && i.password_len >= 12) strength = "Forte";
+          else if ((i.entry_type || "password") === "password" && i.password_len >= 12) strength = "Forte";
 
           return {
             id: i.id,
             title: i.title,
-            type: i.tags && i.tags.length > 0 ? i.tags[0] : "password",
+            type: i.entry_type || "password",
             username: i.username,
             url: i.url,
             updated: updatedAt.toLocaleDateString("pt-PT"),
             strength,
             content: "", // Content is now fetched on-demand
-            has_notes: i.has_notes // Flag for UI
+            has_notes: i.has_notes, // Flag for UI
+            attachments: i.attachments || [] // Image bytes live in separately stored attachment blobs
           };
         })
       );
@@ -1578,7 +1583,36 @@ const MainApp = ({ isDarkMode, setIsDarkMode, onLogout, user, onConnect }) => {
     setRevealedPassword("");
   };
 
-  const revealSecret = (id) => {
+  // Attachment blobs (images) are stored next to the vault blob, one key per attachment
+  const attachmentKey = (id) => `richiesafe_attachment_${id}`;
+
+  const storeSealedAttachments = async () => {
+    for (const att of vaultHandle.take_sealed_attachments()) {
+      await storage.set(attachmentKey(att.id), JSON.stringify(Array.from(att.blob)));
+      att.free();
+    }
+  };
+
+  // Deletes stored blobs of attachments the vault no longer references
+  const removeAttachments = async (ids) => {
+    for (const id of ids) {
+      await storage.remove(attachmentKey(id));
+    }
+  };
+
+  // Decrypts an image entry's attachment into a data URL, or null if it is missing
+  const readImage = async (item) => {
+    const ref = item?.attachments?.[0];
+    if (!ref) return null;
+    const stored = await storage.get(attachmentKey(ref.id));
+    if (!stored) return null;
+    const bytes = vaultHandle.open_attachment(ref.id, new Uint8Array(JSON.parse(stored)));
+    let binary = "";
+    for (const b of bytes) binary += String.fromCharCode(b);
+    return `data:${ref.mime_type || "application/octet-stream"};base64,${btoa(binary)}`;
+  };
+
+  const revealSecret = async (id) => {
     if (isWebMode) {
       alert("Revelar segredos está desativado em Modo Web/Emergência.");
       return;
@@ -1586,11 +1620,19 @@ const MainApp = ({ isDarkMode, setIsDarkMode, onLogout, user, onConnect }) => {
 
     // Fetch from WASM on demand
     try {
+      const item = vaultItems.find((v) => v.id === id);
       const secretBytes = vaultHandle.get_entry_password(id);
       const noteBytes = vaultHandle.get_entry_notes(id);
       const dec = new TextDecoder();
 
-      if (secretBytes) {
+      if (item?.type === "image") {
+        const image = await readImage(item);
+        if (image) {
+          setRevealedPassword(image);
+          setShowPassword(true);
+          scheduleAutoHide();
+        }
+      } else if (secretBytes) {
         setRevealedPassword(dec.decode(secretBytes));
         setShowPassword(true);
         scheduleAutoHide();
@@ -1645,17 +1687,24 @@ const MainApp = ({ isDarkMode, setIsDarkMode, onLogout, user, onConnect }) => {
       const url = "";
       const notes = newItem.notes || "";
 
-      const newId = vaultHandle.add_entry(newItem.type, newItem.title, newItem.user, newItem.pass, url, notes);
-
-      // If editing, delete old first (Strategy: Create New -> if ok -> Delete Old? Or Delete -> Create?)
-      // Since we don't have atomic update, we'll try: Add New -> (if success) -> Delete Old.
-
-      if (editingId) {
-        try {
-          vaultHandle.delete_entry(editingId);
-        } catch (delErr) {
-          console.error("Failed to delete old entry during edit", delErr);
-          // Not fatal
+      // Edits keep the entry id and created_at; only a type change needs a new entry
+      const editing = editingId ? vaultItems.find((v) => v.id === editingId) : null;
+      let dropped = [];
+      if (editing && editing.type === newItem.type) {
+        dropped = vaultHandle.update_entry(editingId, newItem.title, newItem.user, newItem.pass, url, notes);
+        await storeSealedAttachments();
+      } else {
+        vaultHandle.add_entry(newItem.type, newItem.title, newItem.user, newItem.pass, url, notes);
+        await storeSealedAttachments();
+        if (editing) {
+          try {
+            dropped = (editing.attachments || []).map((a) => a.id);
+            vaultHandle.delete_entry(editingId);
+          } catch (delErr) {
+            dropped = [];
+            console.error("Failed to delete old entry during edit", delErr);
+            // Not fatal
+          }
         }
       }
 
@@ -1669,6 +1718,7 @@ const MainApp = ({ isDarkMode, setIsDarkMode, onLogout, user, onConnect }) => {
 
       // Persist in background (but await to catch errors if needed, though UI is already closed)
       await persistExport();
+      await removeAttachments(dropped);
     } catch (e) {
       alert("Erro ao guardar: " + e);
       console.error(e);
@@ -1681,10 +1731,12 @@ const MainApp = ({ isDarkMode, setIsDarkMode, onLogout, user, onConnect }) => {
       message: "Tens a certeza que queres eliminar este item?",
       onConfirm: async () => {
         try {
+          const attachmentIds = (vaultItems.find((v) => v.id === id)?.attachments || []).map((a) => a.id);
           vaultHandle.delete_entry(id);
           refreshItems();
           closeDetails();
           await persistExport();
+          await removeAttachments(attachmentIds);
         } catch (e) {
           console.error(e);
           alert("Erro ao eliminar: " + e);
@@ -2216,18 +2268,19 @@ const MainApp = ({ isDarkMode, setIsDarkMode, onLogout, user, onConnect }) => {
 
             <div className="mt-8 pt-8 border-t dark:border-slate-800 grid grid-cols-2 gap-4">
               <button
-                onClick={() => {
+                onClick={async () => {
                   // Fetch data on-demand for editing
                   try {
                     const passBytes = vaultHandle.get_entry_password(selectedItem.id);
                     const noteBytes = vaultHandle.get_entry_notes(selectedItem.id);
                     const dec = new TextDecoder();
+                    const image = selectedItem.type === "image" ? await readImage(selectedItem) : null;
 
                     setNewItem({
                       type: selectedItem.type,
                       title: selectedItem.title,
                       user: selectedItem.username || "",
-                      pass: passBytes ? dec.decode(passBytes) : "",
+                      pass: image ?? (passBytes ? dec.decode(passBytes) : ""),
                       notes: noteBytes ? dec.decode(noteBytes) : ""
                     });
                     setEditingId(selectedItem.id);
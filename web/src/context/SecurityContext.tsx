@@ -1,11 +1,12 @@
 import React, { createContext, useContext, useState, useEffect } from 'react';
 // Import from the copied pkg folder (copied by CI workflow or npm script)
 import init, {
-    unlock_vault,
     create_vault_pair,
+    WasmAttemptGuard,
     WasmVaultHandle,
     VaultPair
 } from '../pkg/richiesafe_wasm.js';
+import { storage } from '../utils/storage';
 
 // Import WASM file explicitly as URL to ensure it's included in build assets
 import wasmUrl from '../pkg/richiesafe_wasm_bg.wasm?url';
@@ -24,6 +25,24 @@ interface SecurityContextType {
 
 const SecurityContext = createContext<SecurityContextType | null>(null);
 
+const DEVICE_KEY = "richiesafe_device_key";
+
+const toBytes = (json: string) => new Uint8Array(JSON.parse(json));
+const toJson = (bytes: Uint8Array) => JSON.stringify(Array.from(bytes));
+
+// Signs the attempt records; generated once per device (Keychain/Keystore on mobile)
+async function deviceKey(): Promise<Uint8Array> {
+    const stored = await storage.get(DEVICE_KEY);
+    if (stored) return toBytes(stored);
+    const key = crypto.getRandomValues(new Uint8Array(32));
+    await storage.set(DEVICE_KEY, toJson(key));
+    return key;
+}
+
+// One record per vault (real and decoy), keyed by the header salt at bytes 16-31
+const attemptsKey = (blob: Uint8Array) =>
+    "richiesafe_attempts_" + Array.from(blob.slice(16, 32), b => b.toString(16).padStart(2, "0")).join("");
+
 export function useSecurity() {
     const ctx = useContext(SecurityContext);
     if (!ctx) throw new Error("useSecurity must be used within SecurityProvider");
@@ -84,14 +103,24 @@ export function SecurityProvider({ children }: { children: React.ReactNode }) {
 
     const unlock = async (blob: Uint8Array, secret: string) => {
         if (!isReady) throw new Error("Security module not ready");
+        // Every unlock is throttled; the record is saved whatever the outcome
+        const key = attemptsKey(blob);
+        const stored = await storage.get(key);
+        const guard = new WasmAttemptGuard(blob, await deviceKey(), stored ? toBytes(stored) : undefined);
         try {
-            const handle = unlock_vault(blob, secret);
+            const handle = guard.unlock(blob, secret);
             setVaultHandle(handle);
             setIsAuthenticated(true);
             setError(null);
         } catch (e: any) {
             console.error("Unlock failed", e);
+            if (e?.name === "THROTTLED" || e?.name === "RECOVERY_REQUIRED" || e?.name === "LOCKED_OUT") {
+                throw e;
+            }
             throw new Error("Invalid password or corrupted vault");
+        } finally {
+            await storage.set(key, toJson(guard.record));
+            guard.free();
         }
     };
 
@@ -4,18 +4,39 @@ declare module 'firebase/auth';
 declare module 'firebase/app';
 
 declare module '*/richiesafe_wasm.js' {
+    export type FieldType = 'text' | 'hidden' | 'url' | 'email' | 'date' | 'boolean';
+
     export class WasmVaultHandle {
         free(): void;
+        readonly unlocked_via: 'pin' | 'recovery';
         list_entries_metadata(): any;
         get_entry_password(id: string): Uint8Array | undefined;
         get_entry_notes(id: string): Uint8Array | undefined;
-        add_entry(type: string, title: string, username?: string, password?: string, url?: string, notes?: string): void;
+        get_entry_field_value(id: string, index: number): Uint8Array | undefined;
+        set_entry_fields(id: string, fields: Array<{ name: string; type: FieldType; value: string }>): void;
+        add_entry(type: string, title: string, username?: string, password?: string, url?: string, notes?: string): string;
+        update_entry(id: string, title?: string, username?: string, password?: string, url?: string, notes?: string): string[];
         delete_entry(id: string): void;
+        set_entry_otp(id: string, uri?: string): void;
+        get_entry_totp(id: string): { code: string; expires_in: number };
+        next_hotp_code(id: string): { code: string; expires_in: null };
+        take_sealed_attachments(): WasmAttachment[];
+        add_attachment(entry_id: string, file_name: string, mime_type: string | undefined, data: Uint8Array): WasmAttachment;
+        open_attachment(attachment_id: string, blob: Uint8Array): Uint8Array;
+        remove_attachment(entry_id: string, attachment_id: string): void;
+        externalize_files(): WasmAttachment[];
         change_pin(old_pin: string, new_pin: string): void;
+        rotate_recovery(): string;
         export(): Uint8Array;
         lock(): void;
     }
 
+    export class WasmAttachment {
+        free(): void;
+        readonly id: string;
+        readonly blob: Uint8Array;
+    }
+
     export class VaultPair {
         free(): void;
         real: Uint8Array;
@@ -23,7 +44,37 @@ declare module '*/richiesafe_wasm.js' {
     }
 
     export function unlock_vault(blob: Uint8Array, secret: string): WasmVaultHandle;
+    export class WasmAttemptGuard {
+        constructor(blob: Uint8Array, device_key: Uint8Array, stored?: Uint8Array);
+        free(): void;
+        unlock(blob: Uint8Array, secret: string): WasmVaultHandle;
+        readonly failures: number;
+        readonly record: Uint8Array;
+    }
     export function create_vault_pair(pin_real: string, pin_panic: string, recovery: string): VaultPair;
+    export function estimate_strength(secret: string): {
+        entropy_bits: number;
+        score: 0 | 1 | 2 | 3 | 4;
+        reasons: Array<'short' | 'repeat' | 'sequence' | 'keyboard_walk' | 'date' | 'common_word'>;
+    };
+    export function set_strength_policy(min_length: number, min_score: 0 | 1 | 2 | 3 | 4): void;
+    export function generate_password(options?: {
+        length?: number;
+        lowercase?: boolean;
+        uppercase?: boolean;
+        digits?: boolean;
+        symbols?: boolean;
+        exclude_ambiguous?: boolean;
+        require_each_class?: boolean;
+        exclude_chars?: string;
+        symbol_set?: string | null;
+    }): string;
+    export function generate_passphrase(options?: {
+        words?: number;
+        separator?: string;
+        capitalize?: boolean;
+        include_number?: boolean;
+    }): string;
 
     export default function init(module_or_path?: RequestInfo | URL | Response | BufferSource | WebAssembly.Module): Promise<any>;
 }